@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE "wallet_metrics" DROP COLUMN "wallet_rewards";
+ALTER TABLE "wallet_metrics" DROP COLUMN "wallet_balance_volatility";
+ALTER TABLE "wallet_metrics" DROP COLUMN "transactions_with_new_wallets";
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TABLE "wallet_metrics" ADD COLUMN "wallet_rewards" BIGINT;
+ALTER TABLE "wallet_metrics" ADD COLUMN "wallet_balance_volatility" DOUBLE PRECISION NOT NULL DEFAULT 0;
+ALTER TABLE "wallet_metrics" ADD COLUMN "transactions_with_new_wallets" DOUBLE PRECISION NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE "wallet_report" DROP COLUMN "scoring_policy_version";
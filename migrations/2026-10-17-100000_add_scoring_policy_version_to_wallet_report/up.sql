@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE "wallet_report" ADD COLUMN "scoring_policy_version" TEXT NOT NULL DEFAULT 'default';
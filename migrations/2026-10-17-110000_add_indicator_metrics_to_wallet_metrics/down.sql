@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE "wallet_metrics" DROP COLUMN "indicator_metrics";
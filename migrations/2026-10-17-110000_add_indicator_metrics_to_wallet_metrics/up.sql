@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE "wallet_metrics" ADD COLUMN "indicator_metrics" JSONB NOT NULL DEFAULT '{}';
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE "wallet_metrics" ADD COLUMN "score_contributions" JSONB NOT NULL DEFAULT '{}';
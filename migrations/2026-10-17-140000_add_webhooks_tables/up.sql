@@ -0,0 +1,23 @@
+-- Your SQL goes here
+CREATE TYPE webhook_delivery_status AS ENUM ('pending', 'delivered', 'failed');
+CREATE TABLE "webhooks"(
+	"id" UUID NOT NULL PRIMARY KEY,
+	"user_id" UUID NOT NULL,
+	"wallet_addr" TEXT NOT NULL,
+	"url" TEXT NOT NULL,
+	"secret" TEXT NOT NULL,
+	"created_at" TIMESTAMP NOT NULL
+);
+CREATE INDEX "webhooks_wallet_addr_idx" ON "webhooks"("wallet_addr");
+CREATE TABLE "webhook_deliveries"(
+	"id" UUID NOT NULL PRIMARY KEY,
+	"webhook_id" UUID NOT NULL,
+	"wallet_report_id" UUID NOT NULL,
+	"payload" JSONB NOT NULL,
+	"status" webhook_delivery_status NOT NULL,
+	"attempts" INTEGER NOT NULL,
+	"response_status" INTEGER,
+	"last_error" TEXT,
+	"created_at" TIMESTAMP NOT NULL,
+	"updated_at" TIMESTAMP NOT NULL
+);
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+CREATE TABLE "watchlists"(
+	"id" UUID NOT NULL PRIMARY KEY,
+	"user_id" UUID NOT NULL,
+	"name" TEXT NOT NULL,
+	"refresh_interval_minutes" INTEGER NOT NULL,
+	"created_at" TIMESTAMP NOT NULL
+);
+CREATE TABLE "watchlist_entries"(
+	"watchlist_id" UUID NOT NULL,
+	"wallet_addr" TEXT NOT NULL,
+	"last_enqueued_at" TIMESTAMP,
+	"created_at" TIMESTAMP NOT NULL,
+	PRIMARY KEY ("watchlist_id", "wallet_addr")
+);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX "wallet_report_user_id_idx";
+ALTER TABLE "wallet_report" DROP COLUMN "user_id";
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE "wallet_report" ADD COLUMN "user_id" UUID;
+CREATE INDEX "wallet_report_user_id_idx" ON "wallet_report"("user_id");
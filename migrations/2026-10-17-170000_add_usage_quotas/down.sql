@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS "usage_counters";
+DROP TYPE IF EXISTS usage_counter_kind;
+ALTER TABLE "users" DROP COLUMN "requests_per_minute";
+ALTER TABLE "users" DROP COLUMN "reports_per_day";
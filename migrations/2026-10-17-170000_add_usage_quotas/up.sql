@@ -0,0 +1,11 @@
+-- Your SQL goes here
+ALTER TABLE "users" ADD COLUMN "reports_per_day" INTEGER NOT NULL DEFAULT 20;
+ALTER TABLE "users" ADD COLUMN "requests_per_minute" INTEGER NOT NULL DEFAULT 60;
+CREATE TYPE usage_counter_kind AS ENUM ('requests', 'reports');
+CREATE TABLE "usage_counters"(
+	"user_id" UUID NOT NULL,
+	"kind" usage_counter_kind NOT NULL,
+	"window_start" TIMESTAMP NOT NULL,
+	"count" INTEGER NOT NULL,
+	PRIMARY KEY ("user_id", "kind", "window_start")
+);
@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+-- plaintext keys can't be recovered from their hashes, so rolling back would lock every user out
+DO $$
+BEGIN
+	RAISE EXCEPTION 'api_keys can''t be rolled back: plaintext API keys are only stored hashed';
+END
+$$;
@@ -0,0 +1,26 @@
+-- Your SQL goes here
+CREATE TABLE "api_keys"(
+	"id" UUID NOT NULL PRIMARY KEY,
+	"user_id" UUID NOT NULL,
+	"prefix" TEXT NOT NULL,
+	"key_hash" TEXT NOT NULL,
+	"salt" TEXT NOT NULL,
+	"created_at" TIMESTAMP NOT NULL,
+	"last_used_at" TIMESTAMP,
+	"revoked_at" TIMESTAMP
+);
+CREATE INDEX "api_keys_prefix_idx" ON "api_keys"("prefix");
+CREATE INDEX "api_keys_user_id_idx" ON "api_keys"("user_id");
+
+-- existing keys keep working, hashed the same way as `hash_api_key`: hex(sha256(salt || key))
+INSERT INTO "api_keys"("id", "user_id", "prefix", "key_hash", "salt", "created_at")
+SELECT
+	gen_random_uuid(),
+	"id",
+	left("api_key", 8),
+	encode(sha256(convert_to("salt" || "api_key", 'UTF8')), 'hex'),
+	"salt",
+	"created_at"
+FROM (SELECT "id", "api_key", "created_at", md5(random()::text) AS "salt" FROM "users") AS "salted_users";
+
+ALTER TABLE "users" DROP COLUMN "api_key";
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS "report_jobs";
+DROP TYPE IF EXISTS report_job_state;
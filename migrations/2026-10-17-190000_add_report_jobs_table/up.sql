@@ -0,0 +1,12 @@
+-- Your SQL goes here
+CREATE TYPE report_job_state AS ENUM ('queued', 'fetching', 'scoring', 'generating', 'done', 'failed');
+CREATE TABLE "report_jobs"(
+	"id" UUID NOT NULL PRIMARY KEY,
+	"wallet_addr" TEXT NOT NULL,
+	"user_id" UUID,
+	"state" report_job_state NOT NULL,
+	"error" TEXT,
+	"created_at" TIMESTAMP NOT NULL,
+	"updated_at" TIMESTAMP NOT NULL,
+	"attempts" INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+CREATE UNIQUE INDEX "report_jobs_in_flight_wallet_addr_idx" ON "report_jobs"("wallet_addr")
+WHERE "state" NOT IN ('done', 'failed');
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+CREATE TABLE "cached_transactions"(
+	"signature" TEXT NOT NULL PRIMARY KEY,
+	"slot" BIGINT NOT NULL,
+	"transaction" JSONB NOT NULL,
+	"created_at" TIMESTAMP NOT NULL
+);
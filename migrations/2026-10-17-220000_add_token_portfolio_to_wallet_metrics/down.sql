@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE "wallet_metrics" DROP COLUMN "token_holdings";
+ALTER TABLE "wallet_metrics" DROP COLUMN "token_concentration";
+ALTER TABLE "wallet_metrics" DROP COLUMN "dust_token_account_share";
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TABLE "wallet_metrics" ADD COLUMN "token_holdings" BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE "wallet_metrics" ADD COLUMN "token_concentration" DOUBLE PRECISION NOT NULL DEFAULT 0;
+ALTER TABLE "wallet_metrics" ADD COLUMN "dust_token_account_share" DOUBLE PRECISION NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE "wallet_metrics" DROP COLUMN "wallet_age_days";
+ALTER TABLE "wallet_metrics" DROP COLUMN "first_seen_at";
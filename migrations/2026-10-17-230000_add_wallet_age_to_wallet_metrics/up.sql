@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE "wallet_metrics" ADD COLUMN "wallet_age_days" BIGINT;
+ALTER TABLE "wallet_metrics" ADD COLUMN "first_seen_at" TIMESTAMP;
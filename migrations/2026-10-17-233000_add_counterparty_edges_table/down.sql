@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS "counterparty_edges";
+DROP TYPE IF EXISTS counterparty_direction;
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+CREATE TYPE counterparty_direction AS ENUM ('incoming', 'outgoing');
+CREATE TABLE "counterparty_edges"(
+	"wallet_addr" TEXT NOT NULL,
+	"counterparty_addr" TEXT NOT NULL,
+	"direction" counterparty_direction NOT NULL,
+	"volume_lamports" BIGINT NOT NULL,
+	"tx_count" BIGINT NOT NULL,
+	"first_seen_at" TIMESTAMP NOT NULL,
+	"last_seen_at" TIMESTAMP NOT NULL,
+	PRIMARY KEY ("wallet_addr", "counterparty_addr", "direction")
+);
+CREATE INDEX "counterparty_edges_counterparty_addr_idx" ON "counterparty_edges" ("counterparty_addr");
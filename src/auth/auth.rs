@@ -0,0 +1,202 @@
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::HeaderMap,
+    middleware::Next,
+    Error, HttpMessage, HttpResponse,
+};
+use std::env;
+
+use anyhow::Result;
+use chrono::Utc;
+use log::{error, info};
+
+use crate::database::{
+    models::{ApiKey, User},
+    postgres::Database,
+};
+
+pub const API_KEY_HEADER: &str = "X-API-KEY";
+
+/// Routes which can be called without an API key
+const PUBLIC_PATHS: [&str; 1] = ["/health"];
+/// Routes under this prefix require the `ADMIN_API_KEY` instead of a user's API key
+const ADMIN_PATH_PREFIX: &str = "/admin/";
+
+/// The user owning the API key of the current request and the matching key, available to
+/// handlers as `web::ReqData<AuthenticatedUser>`
+#[derive(Clone, Debug)]
+pub struct AuthenticatedUser(pub User, pub ApiKey);
+
+pub fn api_key(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|api_key| !api_key.is_empty())
+}
+
+fn authenticate(api_key: &str) -> Result<Option<(User, ApiKey)>> {
+    let mut database = Database::connect()?;
+    let authenticated = database.get_user_by_api_key(api_key)?;
+    if let Some((_, key)) = &authenticated {
+        database.mark_api_key_used(key.id, Utc::now().naive_local())?;
+    }
+    Ok(authenticated)
+}
+
+/// Whether the key is the configured `ADMIN_API_KEY`, admin routes are disabled while it's unset
+fn is_admin_key(api_key: &str) -> bool {
+    env::var("ADMIN_API_KEY")
+        .map(|admin_key| !admin_key.is_empty() && admin_key == api_key)
+        .unwrap_or(false)
+}
+
+/// Rejects requests to non public routes which don't carry a valid API key, admin routes only
+/// accept the admin key
+pub async fn require_api_key(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    if PUBLIC_PATHS.contains(&req.path()) {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    }
+
+    let Some(api_key) = api_key(req.headers()) else {
+        info!("Rejected request to {} without API key", req.path());
+        return Ok(req.into_response(HttpResponse::Unauthorized().json("Missing API key")));
+    };
+    if req.path().starts_with(ADMIN_PATH_PREFIX) {
+        if !is_admin_key(api_key) {
+            info!("Rejected request to {} without admin key", req.path());
+            return Ok(req.into_response(HttpResponse::Unauthorized().json("Invalid API key")));
+        }
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    }
+    let (user, key) = match authenticate(api_key) {
+        Ok(Some(authenticated)) => authenticated,
+        Ok(None) => {
+            info!("Rejected request to {} with invalid API key", req.path());
+            return Ok(req.into_response(HttpResponse::Unauthorized().json("Invalid API key")));
+        }
+        Err(err) => {
+            error!("Failed to authenticate request: {:?}", err);
+            return Ok(req.into_response(
+                HttpResponse::InternalServerError().json("Unable to authenticate request"),
+            ));
+        }
+    };
+
+    req.extensions_mut().insert(AuthenticatedUser(user, key));
+    Ok(next.call(req).await?.map_into_boxed_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::{api_key_prefix, hash_api_key};
+    use actix_web::{
+        get, http::StatusCode, middleware::from_fn, post, test as actix_test, App, Responder,
+    };
+
+    #[get("/health")]
+    async fn health() -> impl Responder {
+        HttpResponse::Ok()
+    }
+
+    #[get("/protected")]
+    async fn protected() -> impl Responder {
+        HttpResponse::Ok()
+    }
+
+    #[post("/admin/create_user")]
+    async fn create_user() -> impl Responder {
+        HttpResponse::Ok()
+    }
+
+    #[test]
+    fn test_api_key_verify() {
+        let (key, api_key) = ApiKey::generate(uuid::Uuid::new_v4());
+        assert_eq!(key.prefix, api_key_prefix(&api_key));
+        assert_ne!(key.key_hash, api_key);
+        assert!(key.verify(&api_key));
+        assert!(!key.verify(&api_key[1..]));
+
+        let revoked = ApiKey {
+            revoked_at: Some(key.created_at),
+            ..key
+        };
+        assert!(!revoked.verify(&api_key));
+    }
+
+    #[test]
+    fn test_hash_api_key_matches_migration() {
+        // keys converted by the migration are hashed as hex(sha256(salt || key)) in SQL
+        assert_eq!(
+            hash_api_key("salt", "key"),
+            "4a466ea0657e479545b1d6c2d994824f80d8eecd7030f3092ff42a9bcad751d8"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_public_path_without_api_key() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(from_fn(require_api_key))
+                .service(health)
+                .service(protected),
+        )
+        .await;
+        let req = actix_test::TestRequest::get().uri("/health").to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_protected_path_without_api_key() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(from_fn(require_api_key))
+                .service(health)
+                .service(protected),
+        )
+        .await;
+        let req = actix_test::TestRequest::get()
+            .uri("/protected")
+            .to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_create_user_without_api_key() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(from_fn(require_api_key))
+                .service(create_user),
+        )
+        .await;
+        let req = actix_test::TestRequest::post()
+            .uri("/admin/create_user")
+            .to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_admin_path_with_non_admin_key() {
+        let app = actix_test::init_service(
+            App::new()
+                .wrap(from_fn(require_api_key))
+                .service(create_user),
+        )
+        .await;
+        // a user's key never reaches the database lookup on admin routes
+        let (_, api_key) = ApiKey::generate(uuid::Uuid::new_v4());
+        let req = actix_test::TestRequest::post()
+            .uri("/admin/create_user")
+            .insert_header((API_KEY_HEADER, api_key))
+            .to_request();
+        let resp = actix_test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -1,25 +1,249 @@
 use std::collections::HashMap;
 
-use actix_web::{delete, get, post, web, App, HttpResponse, HttpServer, Responder};
+use actix_web::{
+    delete, get,
+    http::header::{CacheControl, CacheDirective},
+    middleware::from_fn,
+    post, web, App, HttpResponse, HttpServer, Responder,
+};
+use chrono::{NaiveDateTime, Utc};
 use dotenv::dotenv;
 use log::{error, info};
+use serde::Deserialize;
 use uuid::Uuid;
 use SolAnalystAI::{
-    database::models::RatingClassification,
+    auth::auth::{require_api_key, AuthenticatedUser},
+    database::models::{RatingClassification, UsageCounterKind, User},
     jobs::{
         async_jobs::WalletReportJob,
         sync_jobs::{
-            create_user, delete_report, delete_user, get_recent_wallet_reports, get_wallet_report,
+            add_watchlist_wallet, create_report_job, create_user, create_watchlist, create_webhook,
+            delete_report, delete_user, delete_watchlist, delete_webhook, fail_report_job,
+            get_api_keys, get_counterparty_graph, get_existing_report_id,
+            get_recent_wallet_reports, get_report_job, get_usage, get_wallet_report,
             get_wallet_report_case_report, get_wallet_report_classification,
-            get_wallet_report_count, get_wallet_report_creation_date, get_wallet_report_metrics,
-            get_wallet_report_score, get_wallet_reports, get_wallet_reports_by_classification,
+            get_wallet_report_count, get_wallet_report_creation_date, get_wallet_report_diff,
+            get_wallet_report_history, get_wallet_report_metrics, get_wallet_report_score,
+            get_wallet_reports, get_wallet_reports_by_classification, get_watchlist_wallets,
+            get_watchlists, get_webhook_deliveries, get_webhooks, remove_watchlist_wallet,
+            rotate_api_key,
         },
     },
+    progress::progress::{progress_stream, WALLET_REPORT_PROGRESS_TOPIC},
     pulsar::pulsar::PulsarClient,
+    rate_limit::rate_limit::{consume_quota_within_limit, enforce_rate_limit, release_quota},
+    wallet::address::parse_wallet_addr,
+    webhooks::webhooks::validate_webhook_url,
     worker::worker::WALLET_REPUTATION_TOPIC,
 };
 
 const MAX_RECENT_DAYS: i64 = 5;
+/// Longest `max_age_minutes` a report can be reused for, one year
+const MAX_REPORT_AGE_MINUTES: i64 = 60 * 24 * 365;
+
+#[derive(Deserialize)]
+struct CreateWebhookRequest {
+    wallet_addr: String,
+    url: String,
+}
+
+#[post("/create_webhook")]
+async fn create_webhook_endpoint(
+    user: web::ReqData<AuthenticatedUser>,
+    body: web::Json<CreateWebhookRequest>,
+) -> impl Responder {
+    let CreateWebhookRequest { wallet_addr, url } = body.into_inner();
+    if let Err(err) = parse_wallet_addr(&wallet_addr) {
+        return HttpResponse::BadRequest().json(err.to_string());
+    }
+    info!(
+        "Received request to create webhook for wallet address: {}",
+        wallet_addr
+    );
+    if let Err(err) = validate_webhook_url(&url).await {
+        info!("Rejected webhook url {}: {:?}", url, err);
+        return HttpResponse::BadRequest().json("Unable to create webhook");
+    }
+    match create_webhook(user.0.id, wallet_addr, url) {
+        Ok(webhook) => {
+            info!("Successfully created webhook with id: {}", webhook.id);
+            HttpResponse::Ok().json(webhook)
+        }
+        Err(err) => {
+            error!("Failed to create webhook: {:?}", err);
+            HttpResponse::InternalServerError().json("Unable to create webhook")
+        }
+    }
+}
+
+#[get("/get_webhooks")]
+async fn get_webhooks_endpoint(user: web::ReqData<AuthenticatedUser>) -> impl Responder {
+    match get_webhooks(user.0.id) {
+        Ok(webhooks) => HttpResponse::Ok().json(webhooks),
+        Err(err) => {
+            error!("Failed to fetch webhooks: {:?}", err);
+            HttpResponse::InternalServerError().json("Unable to fetch webhooks")
+        }
+    }
+}
+
+#[delete("/delete_webhook/{webhook_id}")]
+async fn delete_webhook_endpoint(
+    user: web::ReqData<AuthenticatedUser>,
+    webhook_id: web::Path<Uuid>,
+) -> impl Responder {
+    info!(
+        "Received delete webhook request for webhook ID: {}",
+        webhook_id
+    );
+    match delete_webhook(user.0.id, *webhook_id) {
+        Ok(true) => HttpResponse::Ok().json("Successfully deleted webhook"),
+        Ok(false) => HttpResponse::NotFound().json("Webhook with supplied id doesn't exist"),
+        Err(err) => {
+            error!("Failed to delete webhook {}: {:?}", webhook_id, err);
+            HttpResponse::InternalServerError().json("Unable to process request")
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CreateWatchlistRequest {
+    name: String,
+    refresh_interval_minutes: i32,
+}
+
+#[post("/create_watchlist")]
+async fn create_watchlist_endpoint(
+    user: web::ReqData<AuthenticatedUser>,
+    body: web::Json<CreateWatchlistRequest>,
+) -> impl Responder {
+    let CreateWatchlistRequest {
+        name,
+        refresh_interval_minutes,
+    } = body.into_inner();
+    info!("Received request to create watchlist: {}", name);
+    match create_watchlist(user.0.id, name, refresh_interval_minutes) {
+        Ok(watchlist) => {
+            info!("Successfully created watchlist with id: {}", watchlist.id);
+            HttpResponse::Ok().json(watchlist)
+        }
+        Err(err) => {
+            error!("Failed to create watchlist: {:?}", err);
+            HttpResponse::BadRequest().json("Unable to create watchlist")
+        }
+    }
+}
+
+#[get("/get_watchlists")]
+async fn get_watchlists_endpoint(user: web::ReqData<AuthenticatedUser>) -> impl Responder {
+    match get_watchlists(user.0.id) {
+        Ok(watchlists) => HttpResponse::Ok().json(watchlists),
+        Err(err) => {
+            error!("Failed to fetch watchlists: {:?}", err);
+            HttpResponse::InternalServerError().json("Unable to fetch watchlists")
+        }
+    }
+}
+
+#[delete("/delete_watchlist/{watchlist_id}")]
+async fn delete_watchlist_endpoint(
+    user: web::ReqData<AuthenticatedUser>,
+    watchlist_id: web::Path<Uuid>,
+) -> impl Responder {
+    info!(
+        "Received delete watchlist request for watchlist ID: {}",
+        watchlist_id
+    );
+    match delete_watchlist(user.0.id, *watchlist_id) {
+        Ok(true) => HttpResponse::Ok().json("Successfully deleted watchlist"),
+        Ok(false) => HttpResponse::NotFound().json("Watchlist with supplied id doesn't exist"),
+        Err(err) => {
+            error!("Failed to delete watchlist {}: {:?}", watchlist_id, err);
+            HttpResponse::InternalServerError().json("Unable to process request")
+        }
+    }
+}
+
+#[get("/get_watchlist_wallets/{watchlist_id}")]
+async fn get_watchlist_wallets_endpoint(
+    user: web::ReqData<AuthenticatedUser>,
+    watchlist_id: web::Path<Uuid>,
+) -> impl Responder {
+    match get_watchlist_wallets(user.0.id, *watchlist_id) {
+        Ok(Some(entries)) => HttpResponse::Ok().json(entries),
+        Ok(None) => HttpResponse::NotFound().json("Watchlist with supplied id doesn't exist"),
+        Err(err) => {
+            error!(
+                "Failed to fetch wallets of watchlist {}: {:?}",
+                watchlist_id, err
+            );
+            HttpResponse::InternalServerError().json("Unable to process request")
+        }
+    }
+}
+
+#[post("/add_watchlist_wallet/{watchlist_id}/{wallet_addr}")]
+async fn add_watchlist_wallet_endpoint(
+    user: web::ReqData<AuthenticatedUser>,
+    path: web::Path<(Uuid, String)>,
+) -> impl Responder {
+    let (watchlist_id, wallet_addr) = path.into_inner();
+    if let Err(err) = parse_wallet_addr(&wallet_addr) {
+        return HttpResponse::BadRequest().json(err.to_string());
+    }
+    info!(
+        "Received request to add wallet address: {} to watchlist: {}",
+        wallet_addr, watchlist_id
+    );
+    match add_watchlist_wallet(user.0.id, watchlist_id, wallet_addr) {
+        Ok(true) => HttpResponse::Ok().json("Successfully added wallet to watchlist"),
+        Ok(false) => HttpResponse::NotFound().json("Watchlist with supplied id doesn't exist"),
+        Err(err) => {
+            error!(
+                "Failed to add wallet to watchlist {}: {:?}",
+                watchlist_id, err
+            );
+            HttpResponse::InternalServerError().json("Unable to process request")
+        }
+    }
+}
+
+#[delete("/remove_watchlist_wallet/{watchlist_id}/{wallet_addr}")]
+async fn remove_watchlist_wallet_endpoint(
+    user: web::ReqData<AuthenticatedUser>,
+    path: web::Path<(Uuid, String)>,
+) -> impl Responder {
+    let (watchlist_id, wallet_addr) = path.into_inner();
+    match remove_watchlist_wallet(user.0.id, watchlist_id, &wallet_addr) {
+        Ok(Some(true)) => HttpResponse::Ok().json("Successfully removed wallet from watchlist"),
+        Ok(Some(false)) => HttpResponse::NotFound().json("Wallet isn't on the watchlist"),
+        Ok(None) => HttpResponse::NotFound().json("Watchlist with supplied id doesn't exist"),
+        Err(err) => {
+            error!(
+                "Failed to remove wallet from watchlist {}: {:?}",
+                watchlist_id, err
+            );
+            HttpResponse::InternalServerError().json("Unable to process request")
+        }
+    }
+}
+
+#[get("/get_webhook_deliveries/{webhook_id}")]
+async fn get_webhook_deliveries_endpoint(
+    user: web::ReqData<AuthenticatedUser>,
+    webhook_id: web::Path<Uuid>,
+) -> impl Responder {
+    match get_webhook_deliveries(user.0.id, *webhook_id) {
+        Ok(deliveries) => HttpResponse::Ok().json(deliveries),
+        Err(err) => {
+            error!(
+                "Failed to fetch deliveries for webhook {}: {:?}",
+                webhook_id, err
+            );
+            HttpResponse::NotFound().json("Webhook with supplied id doesn't exist")
+        }
+    }
+}
 
 #[get("/get_recent_wallet_reports/{days}")]
 async fn get_recent_wallet_reports_endpoint(days: web::Path<i64>) -> impl Responder {
@@ -57,38 +281,67 @@ async fn get_wallet_reports_endpoint(query: web::Query<HashMap<String, String>>)
     }
 }
 
-#[delete("/delete_user/{api_key}")]
-async fn delete_user_endpoint(api_key: web::Path<String>) -> impl Responder {
-    info!("Received delete user request for API key: {}", api_key);
-    match delete_user(api_key.as_str()) {
+#[delete("/delete_user")]
+async fn delete_user_endpoint(user: web::ReqData<AuthenticatedUser>) -> impl Responder {
+    info!("Received delete user request for user ID: {}", user.0.id);
+    match delete_user(user.0.id) {
         Ok(_) => {
-            info!("Successfully deleted user with API key: {}", api_key);
+            info!("Successfully deleted user with ID: {}", user.0.id);
             HttpResponse::Ok().json("Successfully deleted user")
         }
         Err(err) => {
-            error!("Failed to delete user with API key {}: {:?}", api_key, err);
+            error!("Failed to delete user with ID {}: {:?}", user.0.id, err);
             HttpResponse::InternalServerError().json("Unable to process request")
         }
     }
 }
 
 #[delete("/delete_wallet_report/{report_id}")]
-async fn delete_wallet_report_endpoint(report_id: web::Path<Uuid>) -> impl Responder {
+async fn delete_wallet_report_endpoint(
+    user: web::ReqData<AuthenticatedUser>,
+    report_id: web::Path<Uuid>,
+) -> impl Responder {
     info!(
         "Received delete wallet report request for report ID: {}",
         report_id
     );
-    match delete_report(*report_id) {
-        Ok(_) => {
+    match delete_report(*report_id, Some(user.0.id)) {
+        Ok(true) => {
+            info!("Successfully deleted wallet report with ID: {}", report_id);
+            HttpResponse::Ok().json("Successfully deleted wallet report")
+        }
+        Ok(false) => HttpResponse::NotFound()
+            .json("Report with supplied id doesn't exist or wasn't requested by you"),
+        Err(err) => {
+            error!(
+                "Failed to delete wallet report with ID {}: {:?}",
+                report_id, err
+            );
+            HttpResponse::InternalServerError().json("Unable to process request")
+        }
+    }
+}
+
+/// Reports which weren't requested by a user belong to the admin
+#[delete("/admin/delete_wallet_report/{report_id}")]
+async fn admin_delete_wallet_report_endpoint(report_id: web::Path<Uuid>) -> impl Responder {
+    info!(
+        "Received admin delete wallet report request for report ID: {}",
+        report_id
+    );
+    match delete_report(*report_id, None) {
+        Ok(true) => {
             info!("Successfully deleted wallet report with ID: {}", report_id);
             HttpResponse::Ok().json("Successfully deleted wallet report")
         }
+        Ok(false) => HttpResponse::NotFound()
+            .json("Report with supplied id doesn't exist or was requested by a user"),
         Err(err) => {
             error!(
                 "Failed to delete wallet report with ID {}: {:?}",
                 report_id, err
             );
-            HttpResponse::NotFound().json("Report with supplied id doesn't exist")
+            HttpResponse::InternalServerError().json("Unable to process request")
         }
     }
 }
@@ -118,12 +371,12 @@ async fn get_wallet_report_metrics_endpoint(report_id: web::Path<Uuid>) -> impl
     }
 }
 
-#[post("/create_user")]
+#[post("/admin/create_user")]
 async fn create_user_endpoint() -> impl Responder {
     info!("Received request to create user");
     match create_user() {
         Ok(api_key) => {
-            info!("User created successfully with API key: {}", api_key);
+            info!("User created successfully");
             HttpResponse::Ok().json(api_key)
         }
         Err(err) => {
@@ -255,6 +508,141 @@ async fn get_wallet_report_classification_endpoint(report_id: web::Path<Uuid>) -
     }
 }
 
+#[get("/get_wallet_report_history/{wallet_addr}")]
+async fn get_wallet_report_history_endpoint(wallet_addr: web::Path<String>) -> impl Responder {
+    info!(
+        "Received request for wallet report history for wallet address: {}",
+        wallet_addr
+    );
+    match get_wallet_report_history(&wallet_addr) {
+        Ok(history) => {
+            info!(
+                "Successfully retrieved {} wallet reports for wallet address: {}",
+                history.len(),
+                wallet_addr
+            );
+            HttpResponse::Ok().json(history)
+        }
+        Err(err) => {
+            error!(
+                "Failed to get wallet report history for wallet address {}: {:?}",
+                wallet_addr, err
+            );
+            HttpResponse::InternalServerError().json("Unable to fetch wallet report history")
+        }
+    }
+}
+
+#[get("/get_wallet_report_diff/{from_report_id}/{to_report_id}")]
+async fn get_wallet_report_diff_endpoint(path: web::Path<(Uuid, Uuid)>) -> impl Responder {
+    let (from_report_id, to_report_id) = path.into_inner();
+    info!(
+        "Received request for wallet report diff between report IDs: {} and {}",
+        from_report_id, to_report_id
+    );
+    match get_wallet_report_diff(from_report_id, to_report_id) {
+        Ok(diff) => {
+            info!(
+                "Successfully computed diff between report IDs: {} and {}",
+                from_report_id, to_report_id
+            );
+            HttpResponse::Ok().json(diff)
+        }
+        Err(err) => {
+            error!(
+                "Failed to diff report IDs {} and {}: {:?}",
+                from_report_id, to_report_id, err
+            );
+            HttpResponse::NotFound().json("Supplied ids don't exist or belong to different wallets")
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CounterpartyGraphQuery {
+    /// Hops of counterparties to include around the wallet, 1 by default
+    depth: Option<usize>,
+}
+
+#[get("/get_counterparty_graph/{wallet_addr}")]
+async fn get_counterparty_graph_endpoint(
+    wallet_addr: web::Path<String>,
+    query: web::Query<CounterpartyGraphQuery>,
+) -> impl Responder {
+    let depth = query.depth.unwrap_or(1);
+    info!(
+        "Received request for counterparty graph of depth {} for wallet address: {}",
+        depth, wallet_addr
+    );
+    match get_counterparty_graph(&wallet_addr, depth) {
+        Ok(graph) => {
+            info!(
+                "Successfully retrieved {} counterparty edges for wallet address: {}",
+                graph.edges.len(),
+                wallet_addr
+            );
+            HttpResponse::Ok().json(graph)
+        }
+        Err(err) => {
+            error!(
+                "Failed to get counterparty graph for wallet address {}: {:?}",
+                wallet_addr, err
+            );
+            HttpResponse::InternalServerError().json("Unable to fetch counterparty graph")
+        }
+    }
+}
+
+#[get("/get_wallet_report_status/{report_id}")]
+async fn get_wallet_report_status_endpoint(report_id: web::Path<Uuid>) -> impl Responder {
+    match get_report_job(*report_id) {
+        Ok(Some(report_job)) => HttpResponse::Ok().json(report_job),
+        Ok(None) => HttpResponse::NotFound().json("No wallet report was started with supplied id"),
+        Err(err) => {
+            error!("Failed to get status of report ID {}: {:?}", report_id, err);
+            HttpResponse::InternalServerError().json("Unable to fetch wallet report status")
+        }
+    }
+}
+
+#[get("/stream_wallet_report_progress/{report_id}")]
+async fn stream_wallet_report_progress_endpoint(report_id: web::Path<Uuid>) -> impl Responder {
+    info!(
+        "Received request to stream progress of report ID: {}",
+        report_id
+    );
+    // subscribe before sending the current state, so no transition in between is missed as
+    // the progress topic doesn't keep events published before subscribing
+    let pulsar_client = PulsarClient::new().await;
+    let consumer = match pulsar_client
+        .create_progress_consumer(WALLET_REPORT_PROGRESS_TOPIC)
+        .await
+    {
+        Ok(consumer) => consumer,
+        Err(err) => {
+            error!("Failed to subscribe to report progress: {:?}", err);
+            return HttpResponse::InternalServerError().json("Unable to stream wallet report");
+        }
+    };
+
+    // a job which already finished ends the stream with its snapshot
+    let report_job = match get_report_job(*report_id) {
+        Ok(Some(report_job)) => report_job,
+        Ok(None) => {
+            return HttpResponse::NotFound().json("No wallet report was started with supplied id")
+        }
+        Err(err) => {
+            error!("Failed to get status of report ID {}: {:?}", report_id, err);
+            return HttpResponse::InternalServerError().json("Unable to stream wallet report");
+        }
+    };
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .streaming(progress_stream(&report_job, consumer))
+}
+
 #[get("/get_wallet_report/{report_id}")]
 async fn get_wallet_report_endpoint(report_id: web::Path<Uuid>) -> impl Responder {
     info!(
@@ -285,19 +673,153 @@ async fn health_check() -> impl Responder {
     HttpResponse::Ok()
 }
 
+#[get("/get_api_keys")]
+async fn get_api_keys_endpoint(user: web::ReqData<AuthenticatedUser>) -> impl Responder {
+    match get_api_keys(user.0.id) {
+        Ok(api_keys) => HttpResponse::Ok().json(api_keys),
+        Err(err) => {
+            error!("Failed to fetch API keys of user {}: {:?}", user.0.id, err);
+            HttpResponse::InternalServerError().json("Unable to fetch API keys")
+        }
+    }
+}
+
+#[post("/rotate_api_key/{api_key_id}")]
+async fn rotate_api_key_endpoint(
+    user: web::ReqData<AuthenticatedUser>,
+    api_key_id: web::Path<Uuid>,
+) -> impl Responder {
+    info!("Received request to rotate API key with ID: {}", api_key_id);
+    match rotate_api_key(user.0.id, *api_key_id) {
+        Ok(Some(api_key)) => {
+            info!("Successfully rotated API key with ID: {}", api_key_id);
+            HttpResponse::Ok().json(api_key)
+        }
+        Ok(None) => HttpResponse::NotFound().json("API key with supplied id doesn't exist"),
+        Err(err) => {
+            error!("Failed to rotate API key {}: {:?}", api_key_id, err);
+            HttpResponse::InternalServerError().json("Unable to rotate API key")
+        }
+    }
+}
+
+#[get("/get_usage")]
+async fn get_usage_endpoint(user: web::ReqData<AuthenticatedUser>) -> impl Responder {
+    match get_usage(&user.0) {
+        Ok(usage) => HttpResponse::Ok().json(usage),
+        Err(err) => {
+            error!("Failed to fetch usage of user {}: {:?}", user.0.id, err);
+            HttpResponse::InternalServerError().json("Unable to fetch usage")
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct StartWalletReportQuery {
+    /// Return the latest report instead if it was created within this many minutes
+    max_age_minutes: Option<i64>,
+}
+
+/// Gives back the report counted for a request which didn't enqueue a new job
+fn release_report_quota(user: &User, now: NaiveDateTime) {
+    if let Err(err) = release_quota(user, UsageCounterKind::Reports, now) {
+        error!(
+            "Failed to give back wallet report quota of user {}: {:?}",
+            user.id, err
+        );
+    }
+}
+
+/// Responds with `202 Accepted` and the new report's id when a job is enqueued, or with `200 OK`
+/// and the id of the in-flight or fresh report which is reused instead
 #[post("/start_wallet_report/{wallet_addr}")]
-async fn start_wallet_report_endpoint(wallet_addr: web::Path<String>) -> impl Responder {
+async fn start_wallet_report_endpoint(
+    user: web::ReqData<AuthenticatedUser>,
+    wallet_addr: web::Path<String>,
+    query: web::Query<StartWalletReportQuery>,
+) -> impl Responder {
     info!(
         "Received request to start wallet report for wallet address: {}",
         wallet_addr
     );
-    let pulsar_client = PulsarClient::new().await;
-    let mut pulsar_producer = pulsar_client.create_producer(WALLET_REPUTATION_TOPIC).await;
+    if let Err(err) = parse_wallet_addr(&wallet_addr) {
+        info!("Rejected invalid wallet address {}: {}", wallet_addr, err);
+        return HttpResponse::BadRequest().json(err.to_string());
+    }
+    if let Some(max_age_minutes) = query.max_age_minutes {
+        if !(0..=MAX_REPORT_AGE_MINUTES).contains(&max_age_minutes) {
+            info!("Rejected invalid max age of {} minutes", max_age_minutes);
+            return HttpResponse::BadRequest().json(format!(
+                "max_age_minutes must be between 0 and {}",
+                MAX_REPORT_AGE_MINUTES
+            ));
+        }
+    }
+    // reports which are in flight or fresh enough are shared instead of paid for again
+    match get_existing_report_id(&wallet_addr, query.max_age_minutes) {
+        Ok(Some(existing_id)) => {
+            info!(
+                "Returning existing wallet report ID: {} for wallet address: {}",
+                existing_id, wallet_addr
+            );
+            return HttpResponse::Ok().json(existing_id);
+        }
+        Ok(None) => {}
+        Err(err) => {
+            error!(
+                "Failed to look up existing reports for wallet address {}: {:?}",
+                wallet_addr, err
+            );
+            return HttpResponse::InternalServerError().json("Unable to start wallet report");
+        }
+    }
+
+    let now = Utc::now().naive_local();
+    // the report is counted up front so concurrent requests can't exceed the quota, and
+    // given back unless a new job is actually enqueued
+    let report_quota = match consume_quota_within_limit(&user.0, UsageCounterKind::Reports, now) {
+        Ok(report_quota) => report_quota,
+        Err(err) => {
+            error!(
+                "Failed to count wallet report of user {}: {:?}",
+                user.0.id, err
+            );
+            return HttpResponse::InternalServerError().json("Unable to start wallet report");
+        }
+    };
+    if report_quota.exceeded() {
+        info!(
+            "User {} exceeded their daily wallet report quota",
+            user.0.id
+        );
+        return report_quota.exceeded_response(now);
+    }
+
     let id = Uuid::new_v4();
     let job = WalletReportJob {
         report_id: id,
         wallet_addr: wallet_addr.to_string(),
+        user_id: Some(user.0.id),
     };
+    match create_report_job(id, job.wallet_addr.clone(), job.user_id) {
+        Ok(in_flight_id) if in_flight_id != id => {
+            info!(
+                "Wallet report ID: {} was started concurrently for wallet address: {}",
+                in_flight_id, wallet_addr
+            );
+            release_report_quota(&user.0, now);
+            return HttpResponse::Ok().json(in_flight_id);
+        }
+        Ok(_) => {}
+        Err(err) => {
+            error!("Failed to create report job with ID {}: {:?}", id, err);
+            release_report_quota(&user.0, now);
+            return HttpResponse::InternalServerError().json("Unable to start wallet report");
+        }
+    }
+
+    let pulsar_client = PulsarClient::new().await;
+    let mut pulsar_producer = pulsar_client.create_producer(WALLET_REPUTATION_TOPIC).await;
 
     match pulsar_producer.enqueue_job(job).await {
         Ok(_) => {
@@ -305,13 +827,19 @@ async fn start_wallet_report_endpoint(wallet_addr: web::Path<String>) -> impl Re
                 "Successfully enqueued wallet report job with report ID: {}",
                 id
             );
-            HttpResponse::Ok().json(id)
+            let mut response = HttpResponse::Accepted().json(id);
+            report_quota.apply_headers(response.headers_mut(), now);
+            response
         }
         Err(err) => {
             error!(
                 "Failed to enqueue wallet report job for wallet address {}: {:?}",
                 wallet_addr, err
             );
+            if let Err(err) = fail_report_job(id, format!("Unable to enqueue job: {}", err)) {
+                error!("Failed to mark report job {} as failed: {:?}", id, err);
+            }
+            release_report_quota(&user.0, now);
             HttpResponse::InternalServerError().json("Unable to start wallet report")
         }
     }
@@ -322,9 +850,13 @@ async fn main() -> std::io::Result<()> {
     dotenv().ok();
     HttpServer::new(|| {
         App::new()
+            .wrap(from_fn(enforce_rate_limit))
+            .wrap(from_fn(require_api_key))
             .service(health_check)
             .service(get_wallet_report_endpoint)
             .service(start_wallet_report_endpoint)
+            .service(get_wallet_report_status_endpoint)
+            .service(stream_wallet_report_progress_endpoint)
             .service(get_wallet_report_classification_endpoint)
             .service(get_wallet_report_score_endpoint)
             .service(get_wallet_report_case_report_endpoint)
@@ -333,10 +865,27 @@ async fn main() -> std::io::Result<()> {
             .service(get_wallet_report_metrics_endpoint)
             .service(create_user_endpoint)
             .service(delete_wallet_report_endpoint)
+            .service(admin_delete_wallet_report_endpoint)
             .service(delete_user_endpoint)
             .service(get_wallet_reports_endpoint)
             .service(get_wallet_reports_by_classification_endpoint)
             .service(get_recent_wallet_reports_endpoint)
+            .service(get_wallet_report_history_endpoint)
+            .service(get_wallet_report_diff_endpoint)
+            .service(get_counterparty_graph_endpoint)
+            .service(create_webhook_endpoint)
+            .service(get_webhooks_endpoint)
+            .service(delete_webhook_endpoint)
+            .service(get_webhook_deliveries_endpoint)
+            .service(create_watchlist_endpoint)
+            .service(get_watchlists_endpoint)
+            .service(delete_watchlist_endpoint)
+            .service(get_watchlist_wallets_endpoint)
+            .service(add_watchlist_wallet_endpoint)
+            .service(remove_watchlist_wallet_endpoint)
+            .service(get_usage_endpoint)
+            .service(get_api_keys_endpoint)
+            .service(rotate_api_key_endpoint)
     })
     .bind(("127.0.0.1", 8080))?
     .run()
@@ -346,8 +895,19 @@ async fn main() -> std::io::Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use actix_web::{http::StatusCode, test, App};
+    use actix_web::{http::StatusCode, test, App, HttpMessage};
+    use solana_sdk::{
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+    };
     use uuid::Uuid;
+    use SolAnalystAI::{
+        database::{
+            models::{ApiKey, ReportJob, WalletReport},
+            postgres::Database,
+        },
+        reputation::scoring_policy::MAX_RATING_SCORE,
+    };
 
     #[actix_web::test]
     async fn test_health_check() {
@@ -429,15 +989,188 @@ mod tests {
     }
 
     #[actix_web::test]
+    #[ignore = "requires Postgres and Pulsar"]
     async fn test_start_wallet_report_endpoint() {
-        // Initialize the app with the start_wallet_report endpoint
+        dotenv().ok();
         let app = test::init_service(App::new().service(start_wallet_report_endpoint)).await;
-        let fake_wallet_addr = "fake_wallet_address";
+        let user = User::new();
+        let (api_key, _) = ApiKey::generate(user.id);
+        let mut database = Database::connect().unwrap();
+        database.insert_user(user.clone(), api_key.clone()).unwrap();
+        let wallet_addr = Pubkey::new_unique().to_string();
         let req = test::TestRequest::post()
-            .uri(&format!("/start_wallet_report/{}", fake_wallet_addr))
+            .uri(&format!("/start_wallet_report/{}", wallet_addr))
             .to_request();
+        req.extensions_mut()
+            .insert(AuthenticatedUser(user.clone(), api_key));
         let resp = test::call_service(&app, req).await;
-        // As PulsarClient and database dependencies are not configured, we expect an Internal Server Error
-        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+
+        let report_id: Uuid = test::read_body_json(resp).await;
+        let report_job = database.get_report_job(report_id).unwrap().unwrap();
+        assert_eq!(report_job.wallet_addr, wallet_addr);
+        assert_eq!(report_job.user_id, Some(user.id));
+
+        database.delete_user(user.id).unwrap();
+    }
+
+    #[actix_web::test]
+    #[ignore = "requires Postgres"]
+    async fn test_start_wallet_report_endpoint_reuses_existing_report() {
+        dotenv().ok();
+        let app = test::init_service(App::new().service(start_wallet_report_endpoint)).await;
+        let user = User::new();
+        let (api_key, _) = ApiKey::generate(user.id);
+        let mut database = Database::connect().unwrap();
+        database.insert_user(user.clone(), api_key.clone()).unwrap();
+
+        // an in-flight job of the wallet is shared
+        let in_flight_wallet_addr = Keypair::new().pubkey().to_string();
+        let in_flight_id = Uuid::new_v4();
+        database
+            .insert_report_job(&ReportJob::new(
+                in_flight_id,
+                in_flight_wallet_addr.clone(),
+                Some(user.id),
+            ))
+            .unwrap();
+        let req = test::TestRequest::post()
+            .uri(&format!("/start_wallet_report/{}", in_flight_wallet_addr))
+            .to_request();
+        req.extensions_mut()
+            .insert(AuthenticatedUser(user.clone(), api_key.clone()));
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let report_id: Uuid = test::read_body_json(resp).await;
+        assert_eq!(report_id, in_flight_id);
+
+        // as is a completed report younger than the max age
+        let fresh_report_id = Uuid::new_v4();
+        let fresh_wallet_addr = Keypair::new().pubkey().to_string();
+        database
+            .insert_wallet_report(WalletReport {
+                id: fresh_report_id,
+                rating_classification: RatingClassification::AAA,
+                rating_score: MAX_RATING_SCORE,
+                case_report: serde_json::Value::Null,
+                report_creation_date: Utc::now().naive_local(),
+                wallet_addr: fresh_wallet_addr.clone(),
+                scoring_policy_version: "default".to_string(),
+                user_id: None,
+            })
+            .unwrap();
+        let req = test::TestRequest::post()
+            .uri(&format!(
+                "/start_wallet_report/{}?max_age_minutes=60",
+                fresh_wallet_addr
+            ))
+            .to_request();
+        req.extensions_mut()
+            .insert(AuthenticatedUser(user.clone(), api_key));
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let report_id: Uuid = test::read_body_json(resp).await;
+        assert_eq!(report_id, fresh_report_id);
+
+        database.delete_report(fresh_report_id, None).unwrap();
+        database.delete_user(user.id).unwrap();
+    }
+
+    #[actix_web::test]
+    #[ignore = "requires Postgres"]
+    async fn test_delete_wallet_report_endpoint_without_requesting_user() {
+        dotenv().ok();
+        let app = test::init_service(
+            App::new()
+                .service(delete_wallet_report_endpoint)
+                .service(admin_delete_wallet_report_endpoint),
+        )
+        .await;
+        let user = User::new();
+        let (api_key, _) = ApiKey::generate(user.id);
+        let mut database = Database::connect().unwrap();
+        database.insert_user(user.clone(), api_key.clone()).unwrap();
+        let report_id = Uuid::new_v4();
+        database
+            .insert_wallet_report(WalletReport {
+                id: report_id,
+                rating_classification: RatingClassification::AAA,
+                rating_score: MAX_RATING_SCORE,
+                case_report: serde_json::Value::Null,
+                report_creation_date: Utc::now().naive_local(),
+                wallet_addr: Keypair::new().pubkey().to_string(),
+                scoring_policy_version: "default".to_string(),
+                user_id: None,
+            })
+            .unwrap();
+
+        // a report nobody requested isn't any user's to delete
+        let req = test::TestRequest::delete()
+            .uri(&format!("/delete_wallet_report/{}", report_id))
+            .to_request();
+        req.extensions_mut()
+            .insert(AuthenticatedUser(user.clone(), api_key));
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/admin/delete_wallet_report/{}", report_id))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(database.get_wallet_report(report_id).is_err());
+
+        database.delete_user(user.id).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_start_wallet_report_endpoint_unauthenticated() {
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(require_api_key))
+                .service(start_wallet_report_endpoint),
+        )
+        .await;
+        let req = test::TestRequest::post()
+            .uri("/start_wallet_report/CKs1E69a2e9TmH4mKKLrXFF8kD3ZnwKjoEuXa6sz9WqX")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        // Rejected by the middleware before the endpoint looks for a user
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_web::test]
+    async fn test_start_wallet_report_endpoint_invalid_address() {
+        let app = test::init_service(App::new().service(start_wallet_report_endpoint)).await;
+        let user = User::new();
+        let (api_key, _) = ApiKey::generate(user.id);
+        let req = test::TestRequest::post()
+            .uri("/start_wallet_report/fake_wallet_address")
+            .to_request();
+        req.extensions_mut()
+            .insert(AuthenticatedUser(user, api_key));
+        let resp = test::call_service(&app, req).await;
+        // The address is rejected before any dependency is used
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_start_wallet_report_endpoint_invalid_max_age() {
+        let app = test::init_service(App::new().service(start_wallet_report_endpoint)).await;
+        for max_age_minutes in [-1, i64::MAX] {
+            let user = User::new();
+            let (api_key, _) = ApiKey::generate(user.id);
+            let req = test::TestRequest::post()
+                .uri(&format!(
+                    "/start_wallet_report/CKs1E69a2e9TmH4mKKLrXFF8kD3ZnwKjoEuXa6sz9WqX?max_age_minutes={}",
+                    max_age_minutes
+                ))
+                .to_request();
+            req.extensions_mut()
+                .insert(AuthenticatedUser(user, api_key));
+            let resp = test::call_service(&app, req).await;
+            // The max age is rejected before any dependency is used
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        }
     }
 }
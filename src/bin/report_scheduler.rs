@@ -0,0 +1,9 @@
+use dotenv::dotenv;
+use SolAnalystAI::scheduler::scheduler::WatchlistScheduler;
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+    let mut scheduler = WatchlistScheduler::new().await;
+    scheduler.run().await.unwrap();
+}
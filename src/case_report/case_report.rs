@@ -2,11 +2,12 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    openai_client::{openai_client::OpenAIClient, types::GeneratedCaseReportSections},
-    reputation::reputation::Reputation,
+    openai_client::types::GeneratedCaseReportSections, reputation::reputation::Reputation,
     wallet::wallet::Wallet,
 };
 
+use super::generator::CaseReportGenerator;
+
 #[derive(Serialize, Deserialize)]
 pub struct CaseReport {
     title: String,
@@ -15,7 +16,7 @@ pub struct CaseReport {
 
 impl CaseReport {
     pub async fn new(
-        openai_client: &OpenAIClient,
+        generator: &dyn CaseReportGenerator,
         reputation: &Reputation,
         wallet: Wallet,
     ) -> Result<Self> {
@@ -23,7 +24,7 @@ impl CaseReport {
             "Reputation Ratings Analysis of Wallet: {:?}",
             wallet.wallet_addr
         );
-        let sections = openai_client.generate_case_report(&reputation).await?;
+        let sections = generator.generate_case_report(reputation).await?;
 
         Ok(CaseReport { title, sections })
     }
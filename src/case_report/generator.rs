@@ -0,0 +1,245 @@
+use std::env;
+
+use anyhow::{anyhow, Result};
+use futures::{future::BoxFuture, FutureExt};
+use log::info;
+
+use crate::{
+    openai_client::{openai_client::OpenAIClient, types::GeneratedCaseReportSections},
+    reputation::{
+        indicator_registry::IndicatorResult,
+        reputation::{PenaltySeverity, Reputation},
+    },
+};
+
+/// Writes the sections of a case report explaining a wallet's reputation
+pub trait CaseReportGenerator: Send + Sync {
+    fn generate_case_report<'a>(
+        &'a self,
+        reputation: &'a Reputation,
+    ) -> BoxFuture<'a, Result<GeneratedCaseReportSections>>;
+}
+
+impl CaseReportGenerator for OpenAIClient {
+    fn generate_case_report<'a>(
+        &'a self,
+        reputation: &'a Reputation,
+    ) -> BoxFuture<'a, Result<GeneratedCaseReportSections>> {
+        OpenAIClient::generate_case_report(self, reputation).boxed()
+    }
+}
+
+/// Builds the case report from the indicator results alone, so identical reputations always
+/// produce identical reports and no LLM is needed
+pub struct TemplateCaseReportGenerator;
+
+fn indicator_title(indicator: &IndicatorResult) -> String {
+    indicator.name.replace('_', " ")
+}
+
+fn is_penalized(indicator: &IndicatorResult) -> bool {
+    matches!(
+        indicator.penalty.severity,
+        PenaltySeverity::Low | PenaltySeverity::Medium | PenaltySeverity::High
+    )
+}
+
+impl TemplateCaseReportGenerator {
+    pub fn sections(reputation: &Reputation) -> GeneratedCaseReportSections {
+        let indicators = &reputation.indicators;
+        let penalized_count = indicators.iter().filter(|i| is_penalized(i)).count();
+
+        let mut summary = format!(
+            "The wallet is rated {:?} with a score of {} under scoring policy {}. {} of {} indicators incurred a penalty.",
+            reputation.rating_classification,
+            reputation.rating_score,
+            reputation.scoring_policy_version,
+            penalized_count,
+            indicators.len()
+        );
+        if let Some(path) = reputation.funding_source.describe_path() {
+            summary.push_str(&format!(" Funding path: {}.", path));
+        }
+        let reputation_strengths = indicators
+            .iter()
+            .filter_map(|indicator| match indicator.penalty.severity {
+                PenaltySeverity::Bonus => Some(format!(
+                    "{} earned a bonus: {}",
+                    indicator_title(indicator),
+                    indicator.penalty.reasoning.join(", ")
+                )),
+                PenaltySeverity::None => Some(format!(
+                    "{} incurred no penalty",
+                    indicator_title(indicator)
+                )),
+                _ => None,
+            })
+            .collect();
+        let reputation_challenges = indicators
+            .iter()
+            .filter(|indicator| is_penalized(indicator))
+            .map(|indicator| {
+                format!(
+                    "{} incurred a {:?} penalty: {}",
+                    indicator_title(indicator),
+                    indicator.penalty.severity,
+                    indicator.penalty.reasoning.join(", ")
+                )
+            })
+            .collect();
+        let potential_downgrade_factors = indicators
+            .iter()
+            .filter(|indicator| {
+                matches!(
+                    indicator.penalty.severity,
+                    PenaltySeverity::Low | PenaltySeverity::Medium
+                )
+            })
+            .map(|indicator| {
+                format!(
+                    "{} would deduct more points if its {:?} penalty became more severe",
+                    indicator_title(indicator),
+                    indicator.penalty.severity
+                )
+            })
+            .collect();
+        let penalty_breakdown = indicators
+            .iter()
+            .filter(|indicator| indicator.contribution != 0)
+            .map(|indicator| {
+                format!(
+                    "{}: {:+} points ({:?})",
+                    indicator_title(indicator),
+                    indicator.contribution,
+                    indicator.penalty.severity
+                )
+            })
+            .collect();
+
+        GeneratedCaseReportSections {
+            summary,
+            reputation_strengths,
+            reputation_challenges,
+            potential_downgrade_factors,
+            penalty_breakdown,
+        }
+    }
+}
+
+impl CaseReportGenerator for TemplateCaseReportGenerator {
+    fn generate_case_report<'a>(
+        &'a self,
+        reputation: &'a Reputation,
+    ) -> BoxFuture<'a, Result<GeneratedCaseReportSections>> {
+        async move { Ok(Self::sections(reputation)) }.boxed()
+    }
+}
+
+/// Picks the generator from `CASE_REPORT_GENERATOR`: `openai` (default) calls the
+/// OpenAI-compatible endpoint at `OPENAI_BASE_URL`, `template` needs no LLM at all
+pub fn case_report_generator_from_env() -> Result<Box<dyn CaseReportGenerator>> {
+    let generator = env::var("CASE_REPORT_GENERATOR").unwrap_or_else(|_| "openai".to_string());
+    info!("Using {} case report generator", generator);
+    match generator.as_str() {
+        "openai" => Ok(Box::new(OpenAIClient::new())),
+        "template" => Ok(Box::new(TemplateCaseReportGenerator)),
+        _ => Err(anyhow!("Unknown CASE_REPORT_GENERATOR: {}", generator)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::{
+        database::models::{RatingClassification, WalletMetrics},
+        reputation::{funding_source::FundingSource, reputation::ReputationPenalty},
+    };
+
+    use super::*;
+
+    fn create_mock_indicator(
+        name: &str,
+        severity: PenaltySeverity,
+        contribution: i32,
+    ) -> IndicatorResult {
+        IndicatorResult {
+            name: name.to_string(),
+            metric: serde_json::Value::Null,
+            penalty: ReputationPenalty {
+                severity,
+                reasoning: vec![format!("{} reasoning", name)],
+            },
+            weight: 1.0,
+            contribution,
+        }
+    }
+
+    fn create_mock_reputation() -> Reputation {
+        Reputation {
+            id: Uuid::nil(),
+            indicators: vec![
+                create_mock_indicator("wallet_balance", PenaltySeverity::None, 0),
+                create_mock_indicator("tx_per_hour", PenaltySeverity::Low, -5),
+                create_mock_indicator("wallet_rewards", PenaltySeverity::High, -20),
+            ],
+            rating_score: 75,
+            rating_classification: RatingClassification::BBB,
+            wallet_metrics: WalletMetrics {
+                wallet_report_id: Uuid::nil(),
+                transaction_failure_rate: 0.0,
+                avg_prio_fee: 0.0,
+                prio_fee_std_devi: 0.0,
+                days_since_last_block: 0,
+                tx_per_hour: 0,
+                wallet_balance: 0,
+                wallet_rewards: Some(0),
+                wallet_balance_volatility: 0.0,
+                transactions_with_new_wallets: 0.0,
+                token_holdings: 0,
+                token_concentration: 0.0,
+                dust_token_account_share: 0.0,
+                wallet_age_days: None,
+                first_seen_at: None,
+                indicator_metrics: serde_json::Value::Null,
+                score_contributions: serde_json::Value::Null,
+                penalties: serde_json::Value::Null,
+            },
+            funding_source: FundingSource::default(),
+            scoring_policy_version: "1".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_template_case_report() {
+        let reputation = create_mock_reputation();
+        let sections = TemplateCaseReportGenerator
+            .generate_case_report(&reputation)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            sections.summary,
+            "The wallet is rated BBB with a score of 75 under scoring policy 1. 2 of 3 indicators incurred a penalty."
+        );
+        assert_eq!(
+            sections.reputation_strengths,
+            vec!["wallet balance incurred no penalty"]
+        );
+        assert_eq!(
+            sections.reputation_challenges,
+            vec![
+                "tx per hour incurred a Low penalty: tx_per_hour reasoning",
+                "wallet rewards incurred a High penalty: wallet_rewards reasoning",
+            ]
+        );
+        assert_eq!(sections.potential_downgrade_factors.len(), 1);
+        assert_eq!(
+            sections.penalty_breakdown,
+            vec![
+                "tx per hour: -5 points (Low)",
+                "wallet rewards: -20 points (High)"
+            ]
+        );
+    }
+}
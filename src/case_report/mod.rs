@@ -1 +1,2 @@
 pub mod case_report;
+pub mod generator;
@@ -1,7 +1,7 @@
 use std::io::Write;
 
 use anyhow::Result;
-use chrono::{NaiveDateTime, Utc};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
 use diesel::deserialize::{self, FromSql, FromSqlRow};
 use diesel::expression::AsExpression;
 use diesel::pg::{Pg, PgValue};
@@ -10,11 +10,27 @@ use diesel::serialize::{self, IsNull, Output, ToSql};
 use rand::distr::Alphanumeric;
 use rand::{rng, Rng};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
 use crate::case_report::case_report::CaseReport;
 
-const API_KEY_LENGTH: usize = 20;
+const API_KEY_LENGTH: usize = 32;
+const API_KEY_PREFIX_LENGTH: usize = 8;
+const API_KEY_SALT_LENGTH: usize = 32;
+const WEBHOOK_SECRET_LENGTH: usize = 32;
+const DEFAULT_REPORTS_PER_DAY: i32 = 20;
+const DEFAULT_REQUESTS_PER_MINUTE: i32 = 60;
+/// A job which hasn't progressed for this long is assumed to have been lost by the worker
+const REPORT_JOB_TIMEOUT_MINUTES: i64 = 30;
+
+fn random_alphanumeric(length: usize) -> String {
+    rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
 
 #[derive(Debug, AsExpression, FromSqlRow, Serialize, Deserialize, PartialEq, Clone)]
 #[diesel(sql_type = crate::database::schema::sql_types::RatingClassification)]
@@ -86,12 +102,15 @@ impl From<i32> for RatingClassification {
 #[diesel(table_name = crate::database::schema::wallet_report)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct WalletReport {
-    id: Uuid,
+    pub id: Uuid,
     pub rating_classification: RatingClassification,
     pub rating_score: i32,
     pub case_report: serde_json::Value,
     pub report_creation_date: NaiveDateTime,
-    wallet_addr: String,
+    pub wallet_addr: String,
+    pub scoring_policy_version: String,
+    /// User who requested the report, `None` for reports started by the scheduler
+    pub user_id: Option<Uuid>,
 }
 
 impl WalletReport {
@@ -101,6 +120,8 @@ impl WalletReport {
         rating_score: i32,
         case_report: CaseReport,
         wallet_addr: String,
+        scoring_policy_version: String,
+        user_id: Option<Uuid>,
     ) -> Result<Self> {
         Ok(Self {
             id,
@@ -109,10 +130,20 @@ impl WalletReport {
             case_report: serde_json::to_value(case_report)?,
             report_creation_date: Utc::now().naive_local(),
             wallet_addr,
+            scoring_policy_version,
+            user_id,
         })
     }
 }
 
+#[derive(Queryable, Debug, Serialize)]
+pub struct WalletReportHistoryEntry {
+    pub id: Uuid,
+    pub rating_classification: RatingClassification,
+    pub rating_score: i32,
+    pub report_creation_date: NaiveDateTime,
+}
+
 #[derive(Insertable, Queryable, Debug, Serialize, Clone)]
 #[diesel(table_name = crate::database::schema::wallet_metrics)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
@@ -124,6 +155,17 @@ pub struct WalletMetrics {
     pub days_since_last_block: i64,
     pub tx_per_hour: i64,
     pub wallet_balance: i64,
+    pub wallet_rewards: Option<i64>,
+    pub wallet_balance_volatility: f64,
+    pub transactions_with_new_wallets: f64,
+    pub token_holdings: i64,
+    pub token_concentration: f64,
+    pub dust_token_account_share: f64,
+    pub wallet_age_days: Option<i64>,
+    pub first_seen_at: Option<NaiveDateTime>,
+    pub indicator_metrics: serde_json::Value,
+    pub score_contributions: serde_json::Value,
+    pub penalties: serde_json::Value,
 }
 
 #[derive(Insertable, Queryable, Debug, Serialize, Clone)]
@@ -131,25 +173,77 @@ pub struct WalletMetrics {
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct User {
     pub id: Uuid,
-    pub api_key: String,
     pub created_at: NaiveDateTime,
+    pub reports_per_day: i32,
+    pub requests_per_minute: i32,
 }
 
 impl User {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        let rng = rng();
-        let api_key: String = rng
-            .sample_iter(&Alphanumeric)
-            .take(API_KEY_LENGTH)
-            .map(char::from)
-            .collect();
-
         Self {
             id: Uuid::new_v4(),
-            api_key,
             created_at: Utc::now().naive_local(),
+            reports_per_day: DEFAULT_REPORTS_PER_DAY,
+            requests_per_minute: DEFAULT_REQUESTS_PER_MINUTE,
         }
     }
+
+    pub fn quota(&self, kind: UsageCounterKind) -> i32 {
+        match kind {
+            UsageCounterKind::Requests => self.requests_per_minute,
+            UsageCounterKind::Reports => self.reports_per_day,
+        }
+    }
+}
+
+/// Visible part of an API key, used to look up its hash without revealing the key
+pub fn api_key_prefix(api_key: &str) -> &str {
+    &api_key[..api_key.len().min(API_KEY_PREFIX_LENGTH)]
+}
+
+pub fn hash_api_key(salt: &str, api_key: &str) -> String {
+    hex::encode(Sha256::digest(format!("{}{}", salt, api_key)))
+}
+
+#[derive(Insertable, Queryable, Debug, Serialize, Clone)]
+#[diesel(table_name = crate::database::schema::api_keys)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub prefix: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    #[serde(skip_serializing)]
+    pub salt: String,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+impl ApiKey {
+    /// Generates a new key for the user, returning it alongside the plaintext key which is
+    /// only ever shown once
+    pub fn generate(user_id: Uuid) -> (Self, String) {
+        let api_key = random_alphanumeric(API_KEY_LENGTH);
+        let salt = random_alphanumeric(API_KEY_SALT_LENGTH);
+        let key = Self {
+            id: Uuid::new_v4(),
+            user_id,
+            prefix: api_key_prefix(&api_key).to_string(),
+            key_hash: hash_api_key(&salt, &api_key),
+            salt,
+            created_at: Utc::now().naive_local(),
+            last_used_at: None,
+            revoked_at: None,
+        };
+        (key, api_key)
+    }
+
+    pub fn verify(&self, api_key: &str) -> bool {
+        self.revoked_at.is_none() && hash_api_key(&self.salt, api_key) == self.key_hash
+    }
 }
 
 #[derive(Insertable, Queryable, Debug, Serialize, Clone)]
@@ -185,3 +279,360 @@ impl KnownCreditedWallet {
         }
     }
 }
+
+#[derive(Insertable, Queryable, Debug, Serialize, Clone)]
+#[diesel(table_name = crate::database::schema::webhooks)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Webhook {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub wallet_addr: String,
+    pub url: String,
+    /// Shared secret used to sign delivered payloads
+    pub secret: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl Webhook {
+    pub fn new(user_id: Uuid, wallet_addr: String, url: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            user_id,
+            wallet_addr,
+            url,
+            secret: random_alphanumeric(WEBHOOK_SECRET_LENGTH),
+            created_at: Utc::now().naive_local(),
+        }
+    }
+}
+
+#[derive(Debug, AsExpression, FromSqlRow, Serialize, Deserialize, PartialEq, Clone, Copy)]
+#[diesel(sql_type = crate::database::schema::sql_types::WebhookDeliveryStatus)]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl ToSql<crate::database::schema::sql_types::WebhookDeliveryStatus, Pg>
+    for WebhookDeliveryStatus
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            WebhookDeliveryStatus::Pending => out.write_all(b"pending")?,
+            WebhookDeliveryStatus::Delivered => out.write_all(b"delivered")?,
+            WebhookDeliveryStatus::Failed => out.write_all(b"failed")?,
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<crate::database::schema::sql_types::WebhookDeliveryStatus, Pg>
+    for WebhookDeliveryStatus
+{
+    fn from_sql(bytes: PgValue) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"pending" => Ok(WebhookDeliveryStatus::Pending),
+            b"delivered" => Ok(WebhookDeliveryStatus::Delivered),
+            b"failed" => Ok(WebhookDeliveryStatus::Failed),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+#[derive(Insertable, Queryable, Debug, Serialize, Clone)]
+#[diesel(table_name = crate::database::schema::webhook_deliveries)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub wallet_report_id: Uuid,
+    pub payload: serde_json::Value,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl WebhookDelivery {
+    pub fn new(webhook_id: Uuid, wallet_report_id: Uuid, payload: serde_json::Value) -> Self {
+        let now = Utc::now().naive_local();
+        Self {
+            id: Uuid::new_v4(),
+            webhook_id,
+            wallet_report_id,
+            payload,
+            status: WebhookDeliveryStatus::Pending,
+            attempts: 0,
+            response_status: None,
+            last_error: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+#[derive(Insertable, Queryable, Debug, Serialize, Clone)]
+#[diesel(table_name = crate::database::schema::watchlists)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Watchlist {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub refresh_interval_minutes: i32,
+    pub created_at: NaiveDateTime,
+}
+
+impl Watchlist {
+    pub fn new(user_id: Uuid, name: String, refresh_interval_minutes: i32) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            user_id,
+            name,
+            refresh_interval_minutes,
+            created_at: Utc::now().naive_local(),
+        }
+    }
+}
+
+#[derive(Insertable, Queryable, Debug, Serialize, Clone)]
+#[diesel(table_name = crate::database::schema::watchlist_entries)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct WatchlistEntry {
+    pub watchlist_id: Uuid,
+    pub wallet_addr: String,
+    /// When a report was last scheduled for the wallet by this watchlist
+    pub last_enqueued_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+impl WatchlistEntry {
+    pub fn new(watchlist_id: Uuid, wallet_addr: String) -> Self {
+        Self {
+            watchlist_id,
+            wallet_addr,
+            last_enqueued_at: None,
+            created_at: Utc::now().naive_local(),
+        }
+    }
+}
+
+#[derive(Debug, AsExpression, FromSqlRow, Serialize, Deserialize, PartialEq, Clone, Copy)]
+#[diesel(sql_type = crate::database::schema::sql_types::UsageCounterKind)]
+pub enum UsageCounterKind {
+    Requests,
+    Reports,
+}
+
+impl UsageCounterKind {
+    /// Length of the fixed window usage is counted in
+    pub fn window(&self) -> Duration {
+        match self {
+            UsageCounterKind::Requests => Duration::minutes(1),
+            UsageCounterKind::Reports => Duration::days(1),
+        }
+    }
+
+    pub fn window_start(&self, now: NaiveDateTime) -> NaiveDateTime {
+        let window_seconds = self.window().num_seconds();
+        let timestamp = now.and_utc().timestamp();
+        DateTime::from_timestamp(timestamp - timestamp.rem_euclid(window_seconds), 0)
+            .map(|window_start| window_start.naive_utc())
+            .unwrap_or(now)
+    }
+}
+
+impl ToSql<crate::database::schema::sql_types::UsageCounterKind, Pg> for UsageCounterKind {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            UsageCounterKind::Requests => out.write_all(b"requests")?,
+            UsageCounterKind::Reports => out.write_all(b"reports")?,
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<crate::database::schema::sql_types::UsageCounterKind, Pg> for UsageCounterKind {
+    fn from_sql(bytes: PgValue) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"requests" => Ok(UsageCounterKind::Requests),
+            b"reports" => Ok(UsageCounterKind::Reports),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+#[derive(Insertable, Queryable, Debug, Serialize, Clone)]
+#[diesel(table_name = crate::database::schema::usage_counters)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct UsageCounter {
+    pub user_id: Uuid,
+    pub kind: UsageCounterKind,
+    pub window_start: NaiveDateTime,
+    pub count: i32,
+}
+
+impl UsageCounter {
+    pub fn new(user_id: Uuid, kind: UsageCounterKind, window_start: NaiveDateTime) -> Self {
+        Self {
+            user_id,
+            kind,
+            window_start,
+            count: 1,
+        }
+    }
+}
+
+#[derive(Debug, AsExpression, FromSqlRow, Serialize, Deserialize, PartialEq, Clone, Copy)]
+#[diesel(sql_type = crate::database::schema::sql_types::ReportJobState)]
+pub enum ReportJobState {
+    Queued,
+    Fetching,
+    Scoring,
+    Generating,
+    Done,
+    Failed,
+}
+
+impl ReportJobState {
+    /// States a job ends in, a job in any other state is in flight
+    pub const FINISHED: [ReportJobState; 2] = [ReportJobState::Done, ReportJobState::Failed];
+}
+
+impl ToSql<crate::database::schema::sql_types::ReportJobState, Pg> for ReportJobState {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            ReportJobState::Queued => out.write_all(b"queued")?,
+            ReportJobState::Fetching => out.write_all(b"fetching")?,
+            ReportJobState::Scoring => out.write_all(b"scoring")?,
+            ReportJobState::Generating => out.write_all(b"generating")?,
+            ReportJobState::Done => out.write_all(b"done")?,
+            ReportJobState::Failed => out.write_all(b"failed")?,
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<crate::database::schema::sql_types::ReportJobState, Pg> for ReportJobState {
+    fn from_sql(bytes: PgValue) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"queued" => Ok(ReportJobState::Queued),
+            b"fetching" => Ok(ReportJobState::Fetching),
+            b"scoring" => Ok(ReportJobState::Scoring),
+            b"generating" => Ok(ReportJobState::Generating),
+            b"done" => Ok(ReportJobState::Done),
+            b"failed" => Ok(ReportJobState::Failed),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+/// Progress of a wallet report, from being enqueued until its report is stored
+#[derive(Insertable, Queryable, Debug, Serialize, Clone)]
+#[diesel(table_name = crate::database::schema::report_jobs)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ReportJob {
+    pub id: Uuid,
+    pub wallet_addr: String,
+    pub user_id: Option<Uuid>,
+    pub state: ReportJobState,
+    pub error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    /// Number of times a worker started processing the job
+    pub attempts: i32,
+}
+
+impl ReportJob {
+    pub fn new(report_id: Uuid, wallet_addr: String, user_id: Option<Uuid>) -> Self {
+        let now = Utc::now().naive_local();
+        Self {
+            id: report_id,
+            wallet_addr,
+            user_id,
+            state: ReportJobState::Queued,
+            error: None,
+            created_at: now,
+            updated_at: now,
+            attempts: 0,
+        }
+    }
+
+    /// In-flight jobs which haven't progressed since then are assumed lost, e.g. their worker died
+    pub fn stale_before(now: NaiveDateTime) -> NaiveDateTime {
+        now - Duration::minutes(REPORT_JOB_TIMEOUT_MINUTES)
+    }
+}
+
+/// A finalized transaction as returned by `getTransaction`, which can't change anymore
+#[derive(Insertable, Queryable, Debug, Clone)]
+#[diesel(table_name = crate::database::schema::cached_transactions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct CachedTransaction {
+    pub signature: String,
+    pub slot: i64,
+    pub transaction: serde_json::Value,
+    pub created_at: NaiveDateTime,
+}
+
+impl CachedTransaction {
+    pub fn new(signature: String, slot: i64, transaction: serde_json::Value) -> Self {
+        Self {
+            signature,
+            slot,
+            transaction,
+            created_at: Utc::now().naive_local(),
+        }
+    }
+}
+
+/// Direction SOL moved in between an analyzed wallet and a counterparty
+#[derive(
+    Debug, AsExpression, FromSqlRow, Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Copy,
+)]
+#[diesel(sql_type = crate::database::schema::sql_types::CounterpartyDirection)]
+pub enum CounterpartyDirection {
+    Incoming,
+    Outgoing,
+}
+
+impl ToSql<crate::database::schema::sql_types::CounterpartyDirection, Pg>
+    for CounterpartyDirection
+{
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            CounterpartyDirection::Incoming => out.write_all(b"incoming")?,
+            CounterpartyDirection::Outgoing => out.write_all(b"outgoing")?,
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<crate::database::schema::sql_types::CounterpartyDirection, Pg>
+    for CounterpartyDirection
+{
+    fn from_sql(bytes: PgValue) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"incoming" => Ok(CounterpartyDirection::Incoming),
+            b"outgoing" => Ok(CounterpartyDirection::Outgoing),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+/// SOL moved between an analyzed wallet and one of its counterparties, over the transactions
+/// of the wallet's latest report
+#[derive(Insertable, Queryable, Debug, Serialize, Clone, PartialEq)]
+#[diesel(table_name = crate::database::schema::counterparty_edges)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct CounterpartyEdge {
+    pub wallet_addr: String,
+    pub counterparty_addr: String,
+    pub direction: CounterpartyDirection,
+    pub volume_lamports: i64,
+    pub tx_count: i64,
+    pub first_seen_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+}
@@ -1,8 +1,16 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, Utc};
 use diesel::{
-    delete, dsl::insert_into, Connection, ExpressionMethods, OptionalExtension, PgConnection,
-    QueryDsl, RunQueryDsl,
+    connection::{AnsiTransactionManager, TransactionManager},
+    delete,
+    dsl::{insert_into, IntervalDsl},
+    sql_types::Interval,
+    update,
+    upsert::excluded,
+    BoolExpressionMethods, Connection, ExpressionMethods, IntoSql, JoinOnDsl,
+    NullableExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl,
 };
 use log::{error, info};
 use serde_json::from_value;
@@ -12,14 +20,21 @@ use crate::case_report::case_report::CaseReport;
 
 use super::{
     models::{
-        KnownCreditedWallet, KnownDiscreditedWallet, RatingClassification, User, WalletMetrics,
-        WalletReport,
+        api_key_prefix, ApiKey, CachedTransaction, CounterpartyEdge, KnownCreditedWallet,
+        KnownDiscreditedWallet, RatingClassification, ReportJob, ReportJobState, UsageCounter,
+        UsageCounterKind, User, WalletMetrics, WalletReport, WalletReportHistoryEntry, Watchlist,
+        WatchlistEntry, Webhook, WebhookDelivery, WebhookDeliveryStatus,
     },
     schema::{
-        known_credited_wallets, known_discredited_wallets, users, wallet_metrics, wallet_report,
+        api_keys, cached_transactions, counterparty_edges, known_credited_wallets,
+        known_discredited_wallets, report_jobs, usage_counters, users, wallet_metrics,
+        wallet_report, watchlist_entries, watchlists, webhook_deliveries, webhooks,
     },
 };
 
+const CACHED_TRANSACTIONS_PER_INSERT: usize = 1000;
+const COUNTERPARTY_EDGES_PER_INSERT: usize = 1000;
+
 pub struct Database {
     conn: PgConnection,
 }
@@ -36,6 +51,25 @@ impl Database {
         Ok(Self { conn })
     }
 
+    /// Runs the writes of `f` in a single transaction, rolling all of them back if it fails
+    pub fn transaction<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        AnsiTransactionManager::begin_transaction(&mut self.conn)?;
+        match f(self) {
+            Ok(value) => {
+                AnsiTransactionManager::commit_transaction(&mut self.conn)?;
+                Ok(value)
+            }
+            Err(e) => {
+                if let Err(rollback_error) =
+                    AnsiTransactionManager::rollback_transaction(&mut self.conn)
+                {
+                    error!("Failed to roll back transaction: {:?}", rollback_error);
+                }
+                Err(e)
+            }
+        }
+    }
+
     pub fn insert_wallet_report(&mut self, wallet_report: WalletReport) -> Result<()> {
         insert_into(wallet_report::table)
             .values(&wallet_report)
@@ -148,6 +182,45 @@ impl Database {
         count
     }
 
+    pub fn get_latest_wallet_report(&mut self, wallet_addr: &str) -> Result<Option<WalletReport>> {
+        info!(
+            "Fetching latest wallet report for wallet_addr: {}",
+            wallet_addr
+        );
+        Ok(wallet_report::table
+            .filter(wallet_report::wallet_addr.eq(wallet_addr))
+            .order(wallet_report::report_creation_date.desc())
+            .select(wallet_report::all_columns)
+            .first::<WalletReport>(&mut self.conn)
+            .optional()?)
+    }
+
+    pub fn get_wallet_report_history(
+        &mut self,
+        wallet_addr: &str,
+    ) -> Result<Vec<WalletReportHistoryEntry>> {
+        info!(
+            "Fetching wallet report history for wallet_addr: {}",
+            wallet_addr
+        );
+        let history = wallet_report::table
+            .filter(wallet_report::wallet_addr.eq(wallet_addr))
+            .order(wallet_report::report_creation_date.asc())
+            .select((
+                wallet_report::id,
+                wallet_report::rating_classification,
+                wallet_report::rating_score,
+                wallet_report::report_creation_date,
+            ))
+            .load::<WalletReportHistoryEntry>(&mut self.conn)?;
+        info!(
+            "Successfully fetched {} wallet reports for wallet_addr: {}",
+            history.len(),
+            wallet_addr
+        );
+        Ok(history)
+    }
+
     pub fn insert_wallet_metrics(&mut self, wallet_metrics: WalletMetrics) -> Result<()> {
         info!(
             "Inserting wallet metrics for wallet_report_id: {}",
@@ -178,53 +251,170 @@ impl Database {
         Ok(metrics)
     }
 
-    pub fn insert_user(&mut self, user: User) -> Result<()> {
-        info!("Inserting user with api_key: {}", user.api_key);
-        insert_into(users::table)
-            .values(&user)
-            .execute(&mut self.conn)?;
-        info!("Successfully inserted user with api_key: {}", user.api_key);
+    pub fn insert_user(&mut self, user: User, api_key: ApiKey) -> Result<()> {
+        info!("Inserting user with id: {}", user.id);
+        self.conn
+            .transaction::<_, diesel::result::Error, _>(|conn| {
+                insert_into(users::table).values(&user).execute(conn)?;
+                insert_into(api_keys::table)
+                    .values(&api_key)
+                    .execute(conn)?;
+                Ok(())
+            })?;
+        info!(
+            "Successfully inserted user with id: {} and API key prefix: {}",
+            user.id, api_key.prefix
+        );
         Ok(())
     }
 
     pub fn check_user_exists(&mut self, api_key: &str) -> Result<bool> {
-        info!("Checking existence of user with api_key: {}", api_key);
+        info!(
+            "Checking existence of user with API key prefix: {}",
+            api_key_prefix(api_key)
+        );
+        let exists = self.get_user_by_api_key(api_key)?.is_some();
+        info!(
+            "User with API key prefix: {} exists: {}",
+            api_key_prefix(api_key),
+            exists
+        );
+        Ok(exists)
+    }
+
+    /// Finds the user owning an unrevoked API key, along with the matching key
+    pub fn get_user_by_api_key(&mut self, api_key: &str) -> Result<Option<(User, ApiKey)>> {
+        let candidates = api_keys::table
+            .filter(api_keys::prefix.eq(api_key_prefix(api_key)))
+            .filter(api_keys::revoked_at.is_null())
+            .select(api_keys::all_columns)
+            .load::<ApiKey>(&mut self.conn)?;
+        let Some(key) = candidates.into_iter().find(|key| key.verify(api_key)) else {
+            return Ok(None);
+        };
         let user = users::table
-            .filter(users::api_key.eq(api_key.to_string()))
+            .filter(users::id.eq(key.user_id))
             .select(users::all_columns)
             .first::<User>(&mut self.conn)
             .optional()?;
-        let exists = user.is_some();
-        info!("User with api_key: {} exists: {}", api_key, exists);
-        Ok(exists)
+        Ok(user.map(|user| (user, key)))
     }
 
-    pub fn delete_report(&mut self, wallet_report_id: Uuid) -> Result<()> {
-        info!("Deleting report for wallet_report_id: {}", wallet_report_id);
-        self.conn
+    pub fn mark_api_key_used(&mut self, api_key_id: Uuid, used_at: NaiveDateTime) -> Result<()> {
+        update(api_keys::table)
+            .filter(api_keys::id.eq(api_key_id))
+            .set(api_keys::last_used_at.eq(used_at))
+            .execute(&mut self.conn)?;
+        Ok(())
+    }
+
+    pub fn get_user_api_keys(&mut self, user_id: Uuid) -> Result<Vec<ApiKey>> {
+        Ok(api_keys::table
+            .filter(api_keys::user_id.eq(user_id))
+            .order(api_keys::created_at.asc())
+            .select(api_keys::all_columns)
+            .load::<ApiKey>(&mut self.conn)?)
+    }
+
+    /// Revokes one of the user's keys and stores its replacement, returns false if the key
+    /// doesn't exist, belongs to another user or was already revoked
+    pub fn rotate_api_key(
+        &mut self,
+        api_key_id: Uuid,
+        user_id: Uuid,
+        new_api_key: ApiKey,
+    ) -> Result<bool> {
+        info!("Rotating API key with id: {}", api_key_id);
+        let revoked = self
+            .conn
             .transaction::<_, diesel::result::Error, _>(|conn| {
-                delete(wallet_metrics::table)
-                    .filter(wallet_metrics::wallet_report_id.eq(wallet_report_id))
+                let revoked = update(api_keys::table)
+                    .filter(api_keys::id.eq(api_key_id))
+                    .filter(api_keys::user_id.eq(user_id))
+                    .filter(api_keys::revoked_at.is_null())
+                    .set(api_keys::revoked_at.eq(new_api_key.created_at))
                     .execute(conn)?;
+                if revoked > 0 {
+                    insert_into(api_keys::table)
+                        .values(&new_api_key)
+                        .execute(conn)?;
+                }
+                Ok(revoked)
+            })?;
+        Ok(revoked > 0)
+    }
 
-                delete(wallet_report::table)
-                    .filter(wallet_report::id.eq(wallet_report_id))
-                    .execute(conn)?;
-                Ok(())
+    /// Deletes a report and its metrics, only if it was requested by the given user. Reports
+    /// without a requesting user, e.g. those started by the scheduler, are deleted with `None`
+    pub fn delete_report(&mut self, wallet_report_id: Uuid, user_id: Option<Uuid>) -> Result<bool> {
+        info!("Deleting report for wallet_report_id: {}", wallet_report_id);
+        let deleted = self
+            .conn
+            .transaction::<_, diesel::result::Error, _>(|conn| {
+                let report = wallet_report::table.filter(wallet_report::id.eq(wallet_report_id));
+                let deleted = match user_id {
+                    Some(user_id) => {
+                        delete(report.filter(wallet_report::user_id.eq(user_id))).execute(conn)?
+                    }
+                    None => {
+                        delete(report.filter(wallet_report::user_id.is_null())).execute(conn)?
+                    }
+                };
+                if deleted > 0 {
+                    delete(wallet_metrics::table)
+                        .filter(wallet_metrics::wallet_report_id.eq(wallet_report_id))
+                        .execute(conn)?;
+                }
+                Ok(deleted)
             })?;
         info!(
-            "Successfully deleted report and associated metrics for wallet_report_id: {}",
-            wallet_report_id
+            "Deleted {} reports and associated metrics for wallet_report_id: {}",
+            deleted, wallet_report_id
         );
-        Ok(())
+        Ok(deleted > 0)
     }
 
-    pub fn delete_user(&mut self, api_key: &str) -> Result<()> {
-        info!("Deleting user with api_key: {}", api_key);
-        delete(users::table)
-            .filter(users::api_key.eq(api_key))
-            .execute(&mut self.conn)?;
-        info!("Successfully deleted user with api_key: {}", api_key);
+    /// Deletes the user along with everything they own, so none of it outlives them, e.g. a
+    /// webhook which would keep receiving deliveries
+    pub fn delete_user(&mut self, user_id: Uuid) -> Result<()> {
+        info!("Deleting user with id: {}", user_id);
+        self.conn
+            .transaction::<_, diesel::result::Error, _>(|conn| {
+                let user_webhooks: Vec<Uuid> = webhooks::table
+                    .filter(webhooks::user_id.eq(user_id))
+                    .select(webhooks::id)
+                    .load(conn)?;
+                delete(webhook_deliveries::table)
+                    .filter(webhook_deliveries::webhook_id.eq_any(user_webhooks))
+                    .execute(conn)?;
+                delete(webhooks::table)
+                    .filter(webhooks::user_id.eq(user_id))
+                    .execute(conn)?;
+                let user_watchlists: Vec<Uuid> = watchlists::table
+                    .filter(watchlists::user_id.eq(user_id))
+                    .select(watchlists::id)
+                    .load(conn)?;
+                delete(watchlist_entries::table)
+                    .filter(watchlist_entries::watchlist_id.eq_any(user_watchlists))
+                    .execute(conn)?;
+                delete(watchlists::table)
+                    .filter(watchlists::user_id.eq(user_id))
+                    .execute(conn)?;
+                delete(usage_counters::table)
+                    .filter(usage_counters::user_id.eq(user_id))
+                    .execute(conn)?;
+                delete(report_jobs::table)
+                    .filter(report_jobs::user_id.eq(user_id))
+                    .execute(conn)?;
+                delete(api_keys::table)
+                    .filter(api_keys::user_id.eq(user_id))
+                    .execute(conn)?;
+                delete(users::table)
+                    .filter(users::id.eq(user_id))
+                    .execute(conn)?;
+                Ok(())
+            })?;
+        info!("Successfully deleted user with id: {}", user_id);
         Ok(())
     }
 
@@ -261,19 +451,33 @@ impl Database {
         Ok(reports)
     }
 
-    pub fn insert_discredited_wallet(
+    /// Tracks the wallet as discredited as of its latest report
+    pub fn upsert_discredited_wallet(
         &mut self,
         known_discredited_wallet: KnownDiscreditedWallet,
     ) -> Result<()> {
         insert_into(known_discredited_wallets::table)
             .values(known_discredited_wallet)
+            .on_conflict(known_discredited_wallets::wallet_addr)
+            .do_update()
+            .set(
+                known_discredited_wallets::tracked_at
+                    .eq(excluded(known_discredited_wallets::tracked_at)),
+            )
+            .execute(&mut self.conn)?;
+        Ok(())
+    }
+
+    pub fn delete_discredited_wallet(&mut self, wallet_addr: &str) -> Result<()> {
+        delete(known_discredited_wallets::table)
+            .filter(known_discredited_wallets::wallet_addr.eq(wallet_addr))
             .execute(&mut self.conn)?;
         Ok(())
     }
 
     pub fn find_discredited_associates(
         &mut self,
-        associated_wallets: &Vec<String>,
+        associated_wallets: &[String],
     ) -> Result<Vec<KnownDiscreditedWallet>> {
         Ok(known_discredited_wallets::table
             .filter(known_discredited_wallets::wallet_addr.eq_any(associated_wallets))
@@ -281,23 +485,499 @@ impl Database {
             .get_results(&mut self.conn)?)
     }
 
-    pub fn insert_credited_wallet(
+    /// Tracks the wallet as credited as of its latest report
+    pub fn upsert_credited_wallet(
         &mut self,
         known_credited_wallet: KnownCreditedWallet,
     ) -> Result<()> {
         insert_into(known_credited_wallets::table)
             .values(known_credited_wallet)
+            .on_conflict(known_credited_wallets::wallet_addr)
+            .do_update()
+            .set(
+                known_credited_wallets::tracked_at.eq(excluded(known_credited_wallets::tracked_at)),
+            )
+            .execute(&mut self.conn)?;
+        Ok(())
+    }
+
+    pub fn delete_credited_wallet(&mut self, wallet_addr: &str) -> Result<()> {
+        delete(known_credited_wallets::table)
+            .filter(known_credited_wallets::wallet_addr.eq(wallet_addr))
             .execute(&mut self.conn)?;
         Ok(())
     }
 
     pub fn find_credited_associates(
         &mut self,
-        associated_wallets: &Vec<String>,
+        associated_wallets: &[String],
     ) -> Result<Vec<KnownCreditedWallet>> {
         Ok(known_credited_wallets::table
             .filter(known_credited_wallets::wallet_addr.eq_any(associated_wallets))
             .select(known_credited_wallets::all_columns)
             .get_results(&mut self.conn)?)
     }
+
+    pub fn insert_webhook(&mut self, webhook: Webhook) -> Result<()> {
+        info!(
+            "Inserting webhook with id: {} for wallet_addr: {}",
+            webhook.id, webhook.wallet_addr
+        );
+        insert_into(webhooks::table)
+            .values(&webhook)
+            .execute(&mut self.conn)?;
+        Ok(())
+    }
+
+    pub fn get_user_webhooks(&mut self, user_id: Uuid) -> Result<Vec<Webhook>> {
+        Ok(webhooks::table
+            .filter(webhooks::user_id.eq(user_id))
+            .select(webhooks::all_columns)
+            .load::<Webhook>(&mut self.conn)?)
+    }
+
+    pub fn get_wallet_webhooks(&mut self, wallet_addr: &str) -> Result<Vec<Webhook>> {
+        Ok(webhooks::table
+            .filter(webhooks::wallet_addr.eq(wallet_addr))
+            .select(webhooks::all_columns)
+            .load::<Webhook>(&mut self.conn)?)
+    }
+
+    /// Deletes a webhook owned by the user, returning whether one was deleted
+    pub fn delete_webhook(&mut self, webhook_id: Uuid, user_id: Uuid) -> Result<bool> {
+        info!("Deleting webhook with id: {}", webhook_id);
+        let deleted = self
+            .conn
+            .transaction::<_, diesel::result::Error, _>(|conn| {
+                let deleted = delete(webhooks::table)
+                    .filter(webhooks::id.eq(webhook_id))
+                    .filter(webhooks::user_id.eq(user_id))
+                    .execute(conn)?;
+                if deleted > 0 {
+                    delete(webhook_deliveries::table)
+                        .filter(webhook_deliveries::webhook_id.eq(webhook_id))
+                        .execute(conn)?;
+                }
+                Ok(deleted)
+            })?;
+        Ok(deleted > 0)
+    }
+
+    pub fn insert_webhook_delivery(&mut self, webhook_delivery: &WebhookDelivery) -> Result<()> {
+        insert_into(webhook_deliveries::table)
+            .values(webhook_delivery)
+            .execute(&mut self.conn)?;
+        Ok(())
+    }
+
+    pub fn update_webhook_delivery(&mut self, webhook_delivery: &WebhookDelivery) -> Result<()> {
+        update(webhook_deliveries::table)
+            .filter(webhook_deliveries::id.eq(webhook_delivery.id))
+            .set((
+                webhook_deliveries::status.eq(webhook_delivery.status),
+                webhook_deliveries::attempts.eq(webhook_delivery.attempts),
+                webhook_deliveries::response_status.eq(webhook_delivery.response_status),
+                webhook_deliveries::last_error.eq(&webhook_delivery.last_error),
+                webhook_deliveries::updated_at.eq(webhook_delivery.updated_at),
+            ))
+            .execute(&mut self.conn)?;
+        Ok(())
+    }
+
+    /// Deliveries which haven't succeeded within the attempt limit and haven't been touched
+    /// since `updated_before`, paired with their webhook
+    pub fn get_unfinished_webhook_deliveries(
+        &mut self,
+        max_attempts: i32,
+        updated_before: NaiveDateTime,
+        limit: i64,
+    ) -> Result<Vec<(Webhook, WebhookDelivery)>> {
+        let deliveries: Vec<WebhookDelivery> = webhook_deliveries::table
+            .filter(webhook_deliveries::status.ne(WebhookDeliveryStatus::Delivered))
+            .filter(webhook_deliveries::attempts.lt(max_attempts))
+            .filter(webhook_deliveries::updated_at.lt(updated_before))
+            .order(webhook_deliveries::updated_at.asc())
+            .limit(limit)
+            .select(webhook_deliveries::all_columns)
+            .load(&mut self.conn)?;
+        let webhook_ids: Vec<Uuid> = deliveries.iter().map(|d| d.webhook_id).collect();
+        let webhooks: HashMap<Uuid, Webhook> = webhooks::table
+            .filter(webhooks::id.eq_any(webhook_ids))
+            .select(webhooks::all_columns)
+            .load::<Webhook>(&mut self.conn)?
+            .into_iter()
+            .map(|webhook| (webhook.id, webhook))
+            .collect();
+        Ok(deliveries
+            .into_iter()
+            .filter_map(|delivery| {
+                webhooks
+                    .get(&delivery.webhook_id)
+                    .map(|webhook| (webhook.clone(), delivery))
+            })
+            .collect())
+    }
+
+    pub fn get_webhook_deliveries(
+        &mut self,
+        webhook_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<WebhookDelivery>> {
+        let webhook_exists = webhooks::table
+            .filter(webhooks::id.eq(webhook_id))
+            .filter(webhooks::user_id.eq(user_id))
+            .count()
+            .get_result::<i64>(&mut self.conn)?
+            > 0;
+        if !webhook_exists {
+            return Err(anyhow::Error::msg("Webhook doesn't exist"));
+        }
+
+        Ok(webhook_deliveries::table
+            .filter(webhook_deliveries::webhook_id.eq(webhook_id))
+            .order(webhook_deliveries::created_at.desc())
+            .select(webhook_deliveries::all_columns)
+            .load::<WebhookDelivery>(&mut self.conn)?)
+    }
+
+    pub fn insert_watchlist(&mut self, watchlist: Watchlist) -> Result<()> {
+        info!("Inserting watchlist with id: {}", watchlist.id);
+        insert_into(watchlists::table)
+            .values(&watchlist)
+            .execute(&mut self.conn)?;
+        Ok(())
+    }
+
+    pub fn get_user_watchlists(&mut self, user_id: Uuid) -> Result<Vec<Watchlist>> {
+        Ok(watchlists::table
+            .filter(watchlists::user_id.eq(user_id))
+            .select(watchlists::all_columns)
+            .load::<Watchlist>(&mut self.conn)?)
+    }
+
+    pub fn get_user_watchlist(
+        &mut self,
+        watchlist_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<Watchlist>> {
+        Ok(watchlists::table
+            .filter(watchlists::id.eq(watchlist_id))
+            .filter(watchlists::user_id.eq(user_id))
+            .select(watchlists::all_columns)
+            .first::<Watchlist>(&mut self.conn)
+            .optional()?)
+    }
+
+    /// Deletes a watchlist owned by the user and its entries, returning whether one was deleted
+    pub fn delete_watchlist(&mut self, watchlist_id: Uuid, user_id: Uuid) -> Result<bool> {
+        info!("Deleting watchlist with id: {}", watchlist_id);
+        let deleted = self
+            .conn
+            .transaction::<_, diesel::result::Error, _>(|conn| {
+                let deleted = delete(watchlists::table)
+                    .filter(watchlists::id.eq(watchlist_id))
+                    .filter(watchlists::user_id.eq(user_id))
+                    .execute(conn)?;
+                if deleted > 0 {
+                    delete(watchlist_entries::table)
+                        .filter(watchlist_entries::watchlist_id.eq(watchlist_id))
+                        .execute(conn)?;
+                }
+                Ok(deleted)
+            })?;
+        Ok(deleted > 0)
+    }
+
+    pub fn insert_watchlist_entry(&mut self, watchlist_entry: WatchlistEntry) -> Result<()> {
+        info!(
+            "Adding wallet_addr: {} to watchlist: {}",
+            watchlist_entry.wallet_addr, watchlist_entry.watchlist_id
+        );
+        insert_into(watchlist_entries::table)
+            .values(&watchlist_entry)
+            .on_conflict_do_nothing()
+            .execute(&mut self.conn)?;
+        Ok(())
+    }
+
+    pub fn delete_watchlist_entry(
+        &mut self,
+        watchlist_id: Uuid,
+        wallet_addr: &str,
+    ) -> Result<bool> {
+        let deleted = delete(watchlist_entries::table)
+            .filter(watchlist_entries::watchlist_id.eq(watchlist_id))
+            .filter(watchlist_entries::wallet_addr.eq(wallet_addr))
+            .execute(&mut self.conn)?;
+        Ok(deleted > 0)
+    }
+
+    pub fn get_watchlist_entries(&mut self, watchlist_id: Uuid) -> Result<Vec<WatchlistEntry>> {
+        Ok(watchlist_entries::table
+            .filter(watchlist_entries::watchlist_id.eq(watchlist_id))
+            .select(watchlist_entries::all_columns)
+            .load::<WatchlistEntry>(&mut self.conn)?)
+    }
+
+    /// Entries whose wallet hasn't been scheduled within their watchlist's refresh interval
+    pub fn get_stale_watchlist_entries(
+        &mut self,
+        now: NaiveDateTime,
+    ) -> Result<Vec<WatchlistEntry>> {
+        let refresh_interval =
+            1.minute().into_sql::<Interval>() * watchlists::refresh_interval_minutes;
+        Ok(watchlist_entries::table
+            .inner_join(watchlists::table.on(watchlists::id.eq(watchlist_entries::watchlist_id)))
+            .filter(
+                watchlist_entries::last_enqueued_at.is_null().or(
+                    (watchlist_entries::last_enqueued_at + refresh_interval.nullable()).le(now),
+                ),
+            )
+            .select(watchlist_entries::all_columns)
+            .load::<WatchlistEntry>(&mut self.conn)?)
+    }
+
+    pub fn mark_watchlist_entry_enqueued(
+        &mut self,
+        watchlist_entry: &WatchlistEntry,
+        enqueued_at: NaiveDateTime,
+    ) -> Result<()> {
+        update(watchlist_entries::table)
+            .filter(watchlist_entries::watchlist_id.eq(watchlist_entry.watchlist_id))
+            .filter(watchlist_entries::wallet_addr.eq(&watchlist_entry.wallet_addr))
+            .set(watchlist_entries::last_enqueued_at.eq(Some(enqueued_at)))
+            .execute(&mut self.conn)?;
+        Ok(())
+    }
+
+    /// Counts one more use in the given window, returning the usage so far
+    pub fn increment_usage(
+        &mut self,
+        user_id: Uuid,
+        kind: UsageCounterKind,
+        window_start: NaiveDateTime,
+    ) -> Result<i32> {
+        Ok(insert_into(usage_counters::table)
+            .values(&UsageCounter::new(user_id, kind, window_start))
+            .on_conflict((
+                usage_counters::user_id,
+                usage_counters::kind,
+                usage_counters::window_start,
+            ))
+            .do_update()
+            .set(usage_counters::count.eq(usage_counters::count + 1))
+            .returning(usage_counters::count)
+            .get_result(&mut self.conn)?)
+    }
+
+    /// Counts one use unless `limit` uses were already counted in the window, in a single
+    /// statement so concurrent uses can't go over the limit. `None` if it wasn't counted
+    pub fn increment_usage_within_limit(
+        &mut self,
+        user_id: Uuid,
+        kind: UsageCounterKind,
+        window_start: NaiveDateTime,
+        limit: i32,
+    ) -> Result<Option<i32>> {
+        use diesel::query_dsl::methods::FilterDsl;
+
+        if limit <= 0 {
+            return Ok(None);
+        }
+        let usage_counter = UsageCounter::new(user_id, kind, window_start);
+        let upsert = insert_into(usage_counters::table)
+            .values(&usage_counter)
+            .on_conflict((
+                usage_counters::user_id,
+                usage_counters::kind,
+                usage_counters::window_start,
+            ))
+            .do_update()
+            .set(usage_counters::count.eq(usage_counters::count + 1));
+        Ok(FilterDsl::filter(upsert, usage_counters::count.lt(limit))
+            .returning(usage_counters::count)
+            .get_result(&mut self.conn)
+            .optional()?)
+    }
+
+    /// Takes back a counted use which didn't go through
+    pub fn decrement_usage(
+        &mut self,
+        user_id: Uuid,
+        kind: UsageCounterKind,
+        window_start: NaiveDateTime,
+    ) -> Result<()> {
+        update(usage_counters::table)
+            .filter(usage_counters::user_id.eq(user_id))
+            .filter(usage_counters::kind.eq(kind))
+            .filter(usage_counters::window_start.eq(window_start))
+            .filter(usage_counters::count.gt(0))
+            .set(usage_counters::count.eq(usage_counters::count - 1))
+            .execute(&mut self.conn)?;
+        Ok(())
+    }
+
+    pub fn get_usage(
+        &mut self,
+        user_id: Uuid,
+        kind: UsageCounterKind,
+        window_start: NaiveDateTime,
+    ) -> Result<i32> {
+        Ok(usage_counters::table
+            .filter(usage_counters::user_id.eq(user_id))
+            .filter(usage_counters::kind.eq(kind))
+            .filter(usage_counters::window_start.eq(window_start))
+            .select(usage_counters::count)
+            .first::<i32>(&mut self.conn)
+            .optional()?
+            .unwrap_or(0))
+    }
+
+    /// Inserts the job unless another job for the same wallet is in flight, returns whether
+    /// it was inserted
+    pub fn insert_report_job(&mut self, report_job: &ReportJob) -> Result<bool> {
+        let inserted = insert_into(report_jobs::table)
+            .values(report_job)
+            .on_conflict_do_nothing()
+            .execute(&mut self.conn)?;
+        Ok(inserted > 0)
+    }
+
+    /// The queued or processing job of a wallet
+    pub fn get_in_flight_report_job(&mut self, wallet_addr: &str) -> Result<Option<ReportJob>> {
+        Ok(report_jobs::table
+            .filter(report_jobs::wallet_addr.eq(wallet_addr))
+            .filter(report_jobs::state.ne_all(ReportJobState::FINISHED))
+            .select(report_jobs::all_columns)
+            .first::<ReportJob>(&mut self.conn)
+            .optional()?)
+    }
+
+    /// Fails the in-flight jobs which stopped progressing, returning them
+    pub fn fail_stale_report_jobs(&mut self, now: NaiveDateTime) -> Result<Vec<ReportJob>> {
+        Ok(update(report_jobs::table)
+            .filter(report_jobs::state.ne_all(ReportJobState::FINISHED))
+            .filter(report_jobs::updated_at.lt(ReportJob::stale_before(now)))
+            .set((
+                report_jobs::state.eq(ReportJobState::Failed),
+                report_jobs::error.eq(Some("Timed out")),
+                report_jobs::updated_at.eq(now),
+            ))
+            .returning(report_jobs::all_columns)
+            .get_results(&mut self.conn)?)
+    }
+
+    pub fn update_report_job_state(
+        &mut self,
+        report_id: Uuid,
+        state: ReportJobState,
+        error: Option<String>,
+    ) -> Result<()> {
+        info!("Updating report job {} to state: {:?}", report_id, state);
+        update(report_jobs::table)
+            .filter(report_jobs::id.eq(report_id))
+            .set((
+                report_jobs::state.eq(state),
+                report_jobs::error.eq(error),
+                report_jobs::updated_at.eq(Utc::now().naive_local()),
+            ))
+            .execute(&mut self.conn)?;
+        Ok(())
+    }
+
+    /// Moves the job back to fetching for a new attempt, returning the number of attempts, or
+    /// `None` if the job is done or failed already and mustn't be processed again
+    pub fn start_report_job_attempt(&mut self, report_id: Uuid) -> Result<Option<i32>> {
+        info!("Starting new attempt of report job {}", report_id);
+        Ok(update(report_jobs::table)
+            .filter(report_jobs::id.eq(report_id))
+            .filter(report_jobs::state.ne_all(ReportJobState::FINISHED))
+            .set((
+                report_jobs::state.eq(ReportJobState::Fetching),
+                report_jobs::attempts.eq(report_jobs::attempts + 1),
+                report_jobs::updated_at.eq(Utc::now().naive_local()),
+            ))
+            .returning(report_jobs::attempts)
+            .get_result(&mut self.conn)
+            .optional()?)
+    }
+
+    /// Records the error of an attempt which will be retried, leaving the job in flight
+    pub fn record_report_job_error(&mut self, report_id: Uuid, error: String) -> Result<()> {
+        update(report_jobs::table)
+            .filter(report_jobs::id.eq(report_id))
+            .set((
+                report_jobs::error.eq(Some(error)),
+                report_jobs::updated_at.eq(Utc::now().naive_local()),
+            ))
+            .execute(&mut self.conn)?;
+        Ok(())
+    }
+
+    pub fn get_report_job(&mut self, report_id: Uuid) -> Result<Option<ReportJob>> {
+        Ok(report_jobs::table
+            .filter(report_jobs::id.eq(report_id))
+            .select(report_jobs::all_columns)
+            .first::<ReportJob>(&mut self.conn)
+            .optional()?)
+    }
+
+    pub fn get_cached_transactions(
+        &mut self,
+        signatures: &[String],
+    ) -> Result<Vec<CachedTransaction>> {
+        Ok(cached_transactions::table
+            .filter(cached_transactions::signature.eq_any(signatures))
+            .select(cached_transactions::all_columns)
+            .get_results(&mut self.conn)?)
+    }
+
+    /// Transactions cached concurrently by another worker are left as they are
+    pub fn insert_cached_transactions(&mut self, transactions: &[CachedTransaction]) -> Result<()> {
+        // inserted in chunks to stay below Postgres' limit of 65535 bind parameters
+        for chunk in transactions.chunks(CACHED_TRANSACTIONS_PER_INSERT) {
+            insert_into(cached_transactions::table)
+                .values(chunk)
+                .on_conflict_do_nothing()
+                .execute(&mut self.conn)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the edges stored by an earlier report of the wallet, so they always cover the
+    /// transactions analyzed by its latest report
+    pub fn replace_counterparty_edges(
+        &mut self,
+        wallet_addr: &str,
+        edges: &[CounterpartyEdge],
+    ) -> Result<()> {
+        delete(counterparty_edges::table)
+            .filter(counterparty_edges::wallet_addr.eq(wallet_addr))
+            .execute(&mut self.conn)?;
+        // inserted in chunks to stay below Postgres' limit of 65535 bind parameters
+        for chunk in edges.chunks(COUNTERPARTY_EDGES_PER_INSERT) {
+            insert_into(counterparty_edges::table)
+                .values(chunk)
+                .execute(&mut self.conn)?;
+        }
+        Ok(())
+    }
+
+    /// Highest volume edges touching any of the wallets, as analyzed wallet or as counterparty
+    pub fn get_counterparty_edges(
+        &mut self,
+        wallet_addrs: &[String],
+        limit: i64,
+    ) -> Result<Vec<CounterpartyEdge>> {
+        Ok(counterparty_edges::table
+            .filter(
+                counterparty_edges::wallet_addr
+                    .eq_any(wallet_addrs)
+                    .or(counterparty_edges::counterparty_addr.eq_any(wallet_addrs)),
+            )
+            .order(counterparty_edges::volume_lamports.desc())
+            .limit(limit)
+            .select(counterparty_edges::all_columns)
+            .get_results(&mut self.conn)?)
+    }
 }
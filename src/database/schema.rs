@@ -4,6 +4,22 @@ pub mod sql_types {
     #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "rating_classification"))]
     pub struct RatingClassification;
+
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "webhook_delivery_status"))]
+    pub struct WebhookDeliveryStatus;
+
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "usage_counter_kind"))]
+    pub struct UsageCounterKind;
+
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "report_job_state"))]
+    pub struct ReportJobState;
+
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "counterparty_direction"))]
+    pub struct CounterpartyDirection;
 }
 
 diesel::table! {
@@ -15,6 +31,17 @@ diesel::table! {
         days_since_last_block -> Int8,
         tx_per_hour -> Int8,
         wallet_balance -> Int8,
+        wallet_rewards -> Nullable<Int8>,
+        wallet_balance_volatility -> Float8,
+        transactions_with_new_wallets -> Float8,
+        token_holdings -> Int8,
+        token_concentration -> Float8,
+        dust_token_account_share -> Float8,
+        wallet_age_days -> Nullable<Int8>,
+        first_seen_at -> Nullable<Timestamp>,
+        indicator_metrics -> Jsonb,
+        score_contributions -> Jsonb,
+        penalties -> Jsonb,
     }
 }
 
@@ -29,14 +56,42 @@ diesel::table! {
         case_report -> Jsonb,
         report_creation_date -> Timestamp,
         wallet_addr -> Text,
+        scoring_policy_version -> Text,
+        user_id -> Nullable<Uuid>,
     }
 }
 
 diesel::table! {
     users (id) {
         id -> Uuid,
-        api_key -> Text,
         created_at -> Timestamp,
+        reports_per_day -> Int4,
+        requests_per_minute -> Int4,
+    }
+}
+
+diesel::table! {
+    api_keys (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        prefix -> Text,
+        key_hash -> Text,
+        salt -> Text,
+        created_at -> Timestamp,
+        last_used_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::UsageCounterKind;
+
+    usage_counters (user_id, kind, window_start) {
+        user_id -> Uuid,
+        kind -> UsageCounterKind,
+        window_start -> Timestamp,
+        count -> Int4,
     }
 }
 
@@ -54,4 +109,93 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    webhooks (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        wallet_addr -> Text,
+        url -> Text,
+        secret -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::WebhookDeliveryStatus;
+
+    webhook_deliveries (id) {
+        id -> Uuid,
+        webhook_id -> Uuid,
+        wallet_report_id -> Uuid,
+        payload -> Jsonb,
+        status -> WebhookDeliveryStatus,
+        attempts -> Int4,
+        response_status -> Nullable<Int4>,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    watchlists (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        name -> Text,
+        refresh_interval_minutes -> Int4,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    watchlist_entries (watchlist_id, wallet_addr) {
+        watchlist_id -> Uuid,
+        wallet_addr -> Text,
+        last_enqueued_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::ReportJobState;
+
+    report_jobs (id) {
+        id -> Uuid,
+        wallet_addr -> Text,
+        user_id -> Nullable<Uuid>,
+        state -> ReportJobState,
+        error -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        attempts -> Int4,
+    }
+}
+
+diesel::table! {
+    cached_transactions (signature) {
+        signature -> Text,
+        slot -> Int8,
+        transaction -> Jsonb,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::CounterpartyDirection;
+
+    counterparty_edges (wallet_addr, counterparty_addr, direction) {
+        wallet_addr -> Text,
+        counterparty_addr -> Text,
+        direction -> CounterpartyDirection,
+        volume_lamports -> Int8,
+        tx_count -> Int8,
+        first_seen_at -> Timestamp,
+        last_seen_at -> Timestamp,
+    }
+}
+
 diesel::allow_tables_to_appear_in_same_query!(wallet_metrics, wallet_report,);
+diesel::allow_tables_to_appear_in_same_query!(watchlists, watchlist_entries,);
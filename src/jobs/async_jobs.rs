@@ -1,24 +1,44 @@
 use anyhow::Result;
-use log::info;
+use log::{error, info, warn};
 use pulsar::{producer, DeserializeMessage, Error as PulsarError, SerializeMessage};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
     case_report::case_report::CaseReport,
-    database::models::{KnownCreditedWallet, KnownDiscreditedWallet, WalletReport},
-    reputation::reputation::Reputation,
-    wallet::wallet::Wallet,
+    database::models::{KnownCreditedWallet, KnownDiscreditedWallet, ReportJobState, WalletReport},
+    progress::progress::{ReportProgressEvent, ReportProgressStage},
+    pulsar::pulsar::MAX_JOB_RETRY,
+    reputation::{
+        associates::{KnownAssociates, KnownCreditedAssociates, KnownDiscreditedAssociates},
+        counterparties::counterparty_edges,
+        funding_source::FundingSource,
+        indicator_registry::IndicatorContext,
+        reputation::Reputation,
+    },
+    wallet::wallet::{Wallet, WalletError},
+    webhooks::webhooks::ClassificationChangedEvent,
     worker::worker::WalletReportWorker,
 };
 
-const DISCREDITED_SCORE_RATING_BOUNDARY: i32 = 400;
-const CREDITED_SCORE_RATING_BOUNDARY: i32 = 800;
+/// Whether the job won't be redelivered if this attempt fails, the first delivery included
+fn is_final_attempt(attempt: i32) -> bool {
+    attempt > MAX_JOB_RETRY as i32
+}
+
+/// Whether a job error would recur on every retry, so the job shouldn't be retried
+pub fn is_permanent_failure(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<WalletError>()
+        .is_some_and(WalletError::is_permanent)
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct WalletReportJob {
     pub report_id: Uuid,
     pub wallet_addr: String,
+    /// User who requested the report, `None` for reports started by the scheduler
+    #[serde(default)]
+    pub user_id: Option<Uuid>,
 }
 
 impl SerializeMessage for WalletReportJob {
@@ -41,23 +61,163 @@ impl DeserializeMessage for WalletReportJob {
 
 impl WalletReportJob {
     pub async fn do_job(&self, worker: &mut WalletReportWorker) -> Result<()> {
+        let Some(attempt) = worker.database.start_report_job_attempt(self.report_id)? else {
+            // e.g. failed as timed out while it was waiting in the queue
+            warn!(
+                "Skipping report job {} which is already done or failed",
+                self.report_id
+            );
+            return Ok(());
+        };
+        let result = self.generate_report(worker).await;
+        match &result {
+            Ok(_) => {
+                self.report_progress(worker, ReportProgressStage::Stored, None)
+                    .await
+            }
+            // only a failure which won't be retried is terminal
+            Err(e) if is_permanent_failure(e) || is_final_attempt(attempt) => {
+                self.report_progress(
+                    worker,
+                    ReportProgressStage::Failed,
+                    Some(format!("{:#}", e)),
+                )
+                .await
+            }
+            Err(e) => {
+                warn!(
+                    "Attempt {} of report job {} failed and will be retried: {:#}",
+                    attempt, self.report_id, e
+                );
+                if let Err(e) = worker
+                    .database
+                    .record_report_job_error(self.report_id, format!("{:#}", e))
+                {
+                    error!(
+                        "Failed to record error of report job {}: {:?}",
+                        self.report_id, e
+                    );
+                }
+            }
+        }
+        result
+    }
+
+    /// Records the job state reached by completing a stage and publishes it to progress streams
+    async fn report_progress(
+        &self,
+        worker: &mut WalletReportWorker,
+        stage: ReportProgressStage,
+        error: Option<String>,
+    ) {
+        if let Some(state) = stage.job_state() {
+            self.set_state(worker, state, error.clone());
+        }
+        let event = ReportProgressEvent {
+            report_id: self.report_id,
+            stage,
+            error,
+        };
+        if let Err(e) = worker.progress_producer.publish_progress(event).await {
+            error!(
+                "Failed to publish progress of report job {}: {:?}",
+                self.report_id, e
+            );
+        }
+    }
+
+    /// Job status is informational, failing to record it shouldn't fail the report
+    fn set_state(
+        &self,
+        worker: &mut WalletReportWorker,
+        state: ReportJobState,
+        error: Option<String>,
+    ) {
+        if let Err(e) = worker
+            .database
+            .update_report_job_state(self.report_id, state, error)
+        {
+            error!(
+                "Failed to update state of report job {}: {:?}",
+                self.report_id, e
+            );
+        }
+    }
+
+    async fn generate_report(&self, worker: &mut WalletReportWorker) -> Result<()> {
         info!(
             "Starting WalletReportJob for wallet address: {} and report_id: {}",
             self.wallet_addr, self.report_id
         );
-
-        let wallet = Wallet::new(self.wallet_addr.as_str(), &worker.solana_client).await;
+        // RPC failures fail the job to be retried, rather than rating a wallet missing its data
+        let wallet = Wallet::new(self.wallet_addr.as_str(), worker.chain_data.as_ref()).await?;
         info!("Wallet retrieved: {:?}", wallet);
 
-        let reputation = Reputation::new_from_wallet(&wallet, self.report_id.clone());
+        // full transactions may be limited to the most recent part of the history
+        let transactions = worker
+            .chain_data
+            .batch_process_transactions(
+                wallet
+                    .transaction_history
+                    .iter()
+                    .take(worker.history_config.max_confirmed_transactions)
+                    .cloned()
+                    .collect(),
+            )
+            .await;
+        info!(
+            "Fetched {} confirmed transactions for wallet: {}",
+            transactions.len(),
+            self.wallet_addr
+        );
+
+        self.report_progress(worker, ReportProgressStage::FetchedWallet, None)
+            .await;
+        let known_associates = KnownAssociates::new(&self.wallet_addr, &transactions)?;
+        let discredited_associates = KnownDiscreditedAssociates::new_from_associates(
+            &mut worker.database,
+            &known_associates,
+        )?;
+        let credited_associates =
+            KnownCreditedAssociates::new_from_associates(&mut worker.database, &known_associates)?;
+        info!(
+            "Found {} known discredited and {} known credited associates for wallet: {}",
+            discredited_associates.wallets.len(),
+            credited_associates.wallets.len(),
+            self.wallet_addr
+        );
+
+        let funding_source = FundingSource::trace(
+            worker.chain_data.as_ref(),
+            &mut worker.database,
+            &wallet,
+            &worker.scoring_policy,
+        )
+        .await;
+
+        let context = IndicatorContext {
+            wallet: &wallet,
+            confirmed_transactions: &transactions,
+            discredited_associates: &discredited_associates,
+            credited_associates: &credited_associates,
+            funding_source: &funding_source,
+            policy: &worker.scoring_policy,
+        };
+        let reputation =
+            Reputation::new_from_wallet(&context, &worker.indicator_registry, self.report_id);
         info!(
             "Computed reputation for report_id {}: rating_classification = {:?}, rating_score = {}",
             self.report_id, reputation.rating_classification, reputation.rating_score
         );
+        self.report_progress(worker, ReportProgressStage::ReputationComputed, None)
+            .await;
 
         // Generate case report
-        let case_report = CaseReport::new(&worker.openai_client, &reputation, wallet).await?;
+        let case_report =
+            CaseReport::new(worker.case_report_generator.as_ref(), &reputation, wallet).await?;
         info!("Generated case report for wallet: {}", self.wallet_addr);
+        self.report_progress(worker, ReportProgressStage::CaseReportGenerated, None)
+            .await;
 
         // Create wallet report
         let wallet_report = WalletReport::new(
@@ -66,33 +226,62 @@ impl WalletReportJob {
             reputation.rating_score,
             case_report,
             self.wallet_addr.clone(),
+            reputation.scoring_policy_version.clone(),
+            self.user_id,
         )?;
         info!("Wallet report created, proceeding to database insertion");
 
-        // Insert wallet report into database
-        worker.database.insert_wallet_report(wallet_report)?;
-        info!(
-            "Wallet report inserted successfully for report_id {}",
-            self.report_id
-        );
-
-        // Insert wallet metrics into database
-        worker
+        // Fetch the previous report before it is superseded, to detect classification changes
+        let previous_report = worker
             .database
-            .insert_wallet_metrics(reputation.wallet_metrics)?;
+            .get_latest_wallet_report(&self.wallet_addr)?;
+        let classification_changed_event = previous_report.as_ref().and_then(|previous_report| {
+            ClassificationChangedEvent::new(previous_report, &wallet_report)
+        });
+
+        let counterparty_edges = counterparty_edges(&self.wallet_addr, &transactions);
+        let rating_boundaries = &worker.scoring_policy.rating_boundaries;
+
+        // the report is stored together with everything derived from it, so a failed write
+        // leaves nothing behind to collide with when the job is retried
+        worker.database.transaction(|database| {
+            database.insert_wallet_report(wallet_report)?;
+            database.insert_wallet_metrics(reputation.wallet_metrics)?;
+            database.replace_counterparty_edges(&self.wallet_addr, &counterparty_edges)?;
+            // a re-scored wallet is only tracked under its latest rating, so its associates
+            // never get the bonus and the penalty for it at once
+            if reputation.rating_score < rating_boundaries.discredited {
+                database.delete_credited_wallet(&self.wallet_addr)?;
+                database.upsert_discredited_wallet(KnownDiscreditedWallet::new(
+                    self.wallet_addr.clone(),
+                ))?;
+            } else if reputation.rating_score > rating_boundaries.credited {
+                database.delete_discredited_wallet(&self.wallet_addr)?;
+                database
+                    .upsert_credited_wallet(KnownCreditedWallet::new(self.wallet_addr.clone()))?;
+            } else {
+                database.delete_discredited_wallet(&self.wallet_addr)?;
+                database.delete_credited_wallet(&self.wallet_addr)?;
+            }
+            Ok(())
+        })?;
         info!(
-            "Wallet metrics inserted successfully for wallet: {}",
-            self.wallet_addr
+            "Wallet report, metrics and {} counterparty edges stored for report_id {}",
+            counterparty_edges.len(),
+            self.report_id
         );
 
-        if reputation.rating_score < DISCREDITED_SCORE_RATING_BOUNDARY {
-            worker
-                .database
-                .insert_discredited_wallet(KnownDiscreditedWallet::new(self.wallet_addr.clone()))?
-        } else if reputation.rating_score > CREDITED_SCORE_RATING_BOUNDARY {
-            worker
-                .database
-                .insert_credited_wallet(KnownCreditedWallet::new(self.wallet_addr.clone()))?
+        // Webhook failures are recorded per delivery and shouldn't fail the stored report
+        if let Some(event) = classification_changed_event {
+            if let Err(e) = worker
+                .webhook_client
+                .notify_classification_change(&mut worker.database, &event)
+            {
+                error!(
+                    "Failed to notify webhooks for wallet {}: {:?}",
+                    self.wallet_addr, e
+                );
+            }
         }
 
         info!(
@@ -1,14 +1,22 @@
 use crate::{
     case_report::case_report::CaseReport,
     database::{
-        models::{RatingClassification, User, WalletMetrics, WalletReport},
+        models::{
+            ApiKey, RatingClassification, ReportJob, ReportJobState, UsageCounterKind, User,
+            WalletMetrics, WalletReport, WalletReportHistoryEntry, Watchlist, WatchlistEntry,
+            Webhook, WebhookDelivery,
+        },
         postgres::Database,
     },
+    rate_limit::rate_limit::{get_quota_usage, RateLimit},
+    reputation::{counterparties::CounterpartyGraph, report_diff::WalletReportDiff},
 };
-use anyhow::Result;
-use chrono::NaiveDateTime;
+use anyhow::{bail, Result};
+use chrono::{Duration, NaiveDateTime, Utc};
 use uuid::Uuid;
 
+const MIN_WATCHLIST_REFRESH_INTERVAL_MINUTES: i32 = 60;
+
 pub fn get_recent_wallet_reports(days: i64) -> Result<Vec<WalletReport>> {
     let mut database = Database::connect()?;
     database.get_recent_wallet_reports(days)
@@ -26,24 +34,38 @@ pub fn get_wallet_reports(from_score: i32, to_score: i32) -> Result<Vec<WalletRe
     database.get_reports_between_scores(from_score, to_score)
 }
 
-pub fn delete_user(api_key: &str) -> Result<()> {
+pub fn delete_user(user_id: Uuid) -> Result<()> {
     let mut database = Database::connect()?;
-    database.delete_user(api_key)
+    database.delete_user(user_id)
 }
 
-pub fn delete_report(wallet_report_id: Uuid) -> Result<()> {
+pub fn delete_report(wallet_report_id: Uuid, user_id: Option<Uuid>) -> Result<bool> {
     let mut database = Database::connect()?;
-    database.delete_report(wallet_report_id)
+    database.delete_report(wallet_report_id, user_id)
 }
 
 pub fn create_user() -> Result<String> {
     let mut database = Database::connect()?;
     let user = User::new();
-    let api_key = user.api_key.clone();
-    database.insert_user(user)?;
+    let (key, api_key) = ApiKey::generate(user.id);
+    database.insert_user(user, key)?;
     Ok(api_key)
 }
 
+pub fn get_api_keys(user_id: Uuid) -> Result<Vec<ApiKey>> {
+    let mut database = Database::connect()?;
+    database.get_user_api_keys(user_id)
+}
+
+/// Replaces one of the user's keys with a new one, returning the new plaintext key
+pub fn rotate_api_key(user_id: Uuid, api_key_id: Uuid) -> Result<Option<String>> {
+    let mut database = Database::connect()?;
+    let (key, api_key) = ApiKey::generate(user_id);
+    Ok(database
+        .rotate_api_key(api_key_id, user_id, key)?
+        .then_some(api_key))
+}
+
 pub fn get_wallet_report_metrics(report_id: Uuid) -> Result<WalletMetrics> {
     let mut database = Database::connect()?;
     database.get_wallet_metrics(report_id)
@@ -74,7 +96,183 @@ pub fn get_wallet_report_classification(report_id: Uuid) -> Result<RatingClassif
     database.get_wallet_report_classification(report_id)
 }
 
+pub fn get_wallet_report_history(wallet_addr: &str) -> Result<Vec<WalletReportHistoryEntry>> {
+    let mut database = Database::connect()?;
+    database.get_wallet_report_history(wallet_addr)
+}
+
+pub fn get_wallet_report_diff(
+    from_report_id: Uuid,
+    to_report_id: Uuid,
+) -> Result<WalletReportDiff> {
+    let mut database = Database::connect()?;
+    let from_report = database.get_wallet_report(from_report_id)?;
+    let from_metrics = database.get_wallet_metrics(from_report_id)?;
+    let to_report = database.get_wallet_report(to_report_id)?;
+    let to_metrics = database.get_wallet_metrics(to_report_id)?;
+    WalletReportDiff::new((&from_report, &from_metrics), (&to_report, &to_metrics))
+}
+
+pub fn get_counterparty_graph(wallet_addr: &str, depth: usize) -> Result<CounterpartyGraph> {
+    let mut database = Database::connect()?;
+    CounterpartyGraph::load(&mut database, wallet_addr, depth)
+}
+
 pub fn get_wallet_report(report_id: Uuid) -> Result<WalletReport> {
     let mut database = Database::connect()?;
     database.get_wallet_report(report_id)
 }
+
+/// The url must have been checked with `validate_webhook_url` already
+pub fn create_webhook(user_id: Uuid, wallet_addr: String, url: String) -> Result<Webhook> {
+    let mut database = Database::connect()?;
+    let webhook = Webhook::new(user_id, wallet_addr, url);
+    database.insert_webhook(webhook.clone())?;
+    Ok(webhook)
+}
+
+pub fn get_webhooks(user_id: Uuid) -> Result<Vec<Webhook>> {
+    let mut database = Database::connect()?;
+    database.get_user_webhooks(user_id)
+}
+
+pub fn delete_webhook(user_id: Uuid, webhook_id: Uuid) -> Result<bool> {
+    let mut database = Database::connect()?;
+    database.delete_webhook(webhook_id, user_id)
+}
+
+pub fn get_webhook_deliveries(user_id: Uuid, webhook_id: Uuid) -> Result<Vec<WebhookDelivery>> {
+    let mut database = Database::connect()?;
+    database.get_webhook_deliveries(webhook_id, user_id)
+}
+
+pub fn create_watchlist(
+    user_id: Uuid,
+    name: String,
+    refresh_interval_minutes: i32,
+) -> Result<Watchlist> {
+    if refresh_interval_minutes < MIN_WATCHLIST_REFRESH_INTERVAL_MINUTES {
+        bail!(
+            "Refresh interval must be at least {} minutes",
+            MIN_WATCHLIST_REFRESH_INTERVAL_MINUTES
+        );
+    }
+    let mut database = Database::connect()?;
+    let watchlist = Watchlist::new(user_id, name, refresh_interval_minutes);
+    database.insert_watchlist(watchlist.clone())?;
+    Ok(watchlist)
+}
+
+pub fn get_watchlists(user_id: Uuid) -> Result<Vec<Watchlist>> {
+    let mut database = Database::connect()?;
+    database.get_user_watchlists(user_id)
+}
+
+pub fn delete_watchlist(user_id: Uuid, watchlist_id: Uuid) -> Result<bool> {
+    let mut database = Database::connect()?;
+    database.delete_watchlist(watchlist_id, user_id)
+}
+
+/// `None` if the user has no watchlist with the id
+pub fn get_watchlist_wallets(
+    user_id: Uuid,
+    watchlist_id: Uuid,
+) -> Result<Option<Vec<WatchlistEntry>>> {
+    let mut database = Database::connect()?;
+    let Some(watchlist) = database.get_user_watchlist(watchlist_id, user_id)? else {
+        return Ok(None);
+    };
+    Ok(Some(database.get_watchlist_entries(watchlist.id)?))
+}
+
+/// Returns whether the user has a watchlist with the id to add the wallet to
+pub fn add_watchlist_wallet(
+    user_id: Uuid,
+    watchlist_id: Uuid,
+    wallet_addr: String,
+) -> Result<bool> {
+    let mut database = Database::connect()?;
+    let Some(watchlist) = database.get_user_watchlist(watchlist_id, user_id)? else {
+        return Ok(false);
+    };
+    database.insert_watchlist_entry(WatchlistEntry::new(watchlist.id, wallet_addr))?;
+    Ok(true)
+}
+
+/// Returns whether the wallet was removed, `None` if the user has no watchlist with the id
+pub fn remove_watchlist_wallet(
+    user_id: Uuid,
+    watchlist_id: Uuid,
+    wallet_addr: &str,
+) -> Result<Option<bool>> {
+    let mut database = Database::connect()?;
+    let Some(watchlist) = database.get_user_watchlist(watchlist_id, user_id)? else {
+        return Ok(None);
+    };
+    Ok(Some(
+        database.delete_watchlist_entry(watchlist.id, wallet_addr)?,
+    ))
+}
+
+pub fn get_usage(user: &User) -> Result<Vec<RateLimit>> {
+    let now = Utc::now().naive_local();
+    Ok(vec![
+        get_quota_usage(user, UsageCounterKind::Requests, now)?,
+        get_quota_usage(user, UsageCounterKind::Reports, now)?,
+    ])
+}
+
+/// Creates a queued job for the wallet, unless one is already in flight. Returns the id of
+/// the in flight job, which is `report_id` if the new job was created
+pub fn create_report_job(
+    report_id: Uuid,
+    wallet_addr: String,
+    user_id: Option<Uuid>,
+) -> Result<Uuid> {
+    let mut database = Database::connect()?;
+    if let Some(report_job) = database.get_in_flight_report_job(&wallet_addr)? {
+        return Ok(report_job.id);
+    }
+    if database.insert_report_job(&ReportJob::new(report_id, wallet_addr.clone(), user_id))? {
+        return Ok(report_id);
+    }
+    // another request created a job for the wallet in between
+    match database.get_in_flight_report_job(&wallet_addr)? {
+        Some(report_job) => Ok(report_job.id),
+        None => bail!("Unable to create report job for wallet {}", wallet_addr),
+    }
+}
+
+/// Id of a report which is in flight for the wallet, or was completed within the last
+/// `max_age_minutes` if given
+pub fn get_existing_report_id(
+    wallet_addr: &str,
+    max_age_minutes: Option<i64>,
+) -> Result<Option<Uuid>> {
+    let mut database = Database::connect()?;
+    if let Some(max_age_minutes) = max_age_minutes {
+        let Some(fresh_from) = Duration::try_minutes(max_age_minutes)
+            .and_then(|max_age| Utc::now().naive_local().checked_sub_signed(max_age))
+        else {
+            bail!("Report max age out of range: {} minutes", max_age_minutes);
+        };
+        if let Some(report) = database.get_latest_wallet_report(wallet_addr)? {
+            if report.report_creation_date >= fresh_from {
+                return Ok(Some(report.id));
+            }
+        }
+    }
+    Ok(database
+        .get_in_flight_report_job(wallet_addr)?
+        .map(|report_job| report_job.id))
+}
+
+pub fn fail_report_job(report_id: Uuid, error: String) -> Result<()> {
+    let mut database = Database::connect()?;
+    database.update_report_job_state(report_id, ReportJobState::Failed, Some(error))
+}
+
+pub fn get_report_job(report_id: Uuid) -> Result<Option<ReportJob>> {
+    let mut database = Database::connect()?;
+    database.get_report_job(report_id)
+}
@@ -1,9 +1,16 @@
+pub mod auth;
 pub mod case_report;
 pub mod database;
 pub mod jobs;
 pub mod openai_client;
+pub mod progress;
 pub mod pulsar;
+pub mod rate_limit;
 pub mod reputation;
+pub mod scheduler;
 pub mod solana_client;
+#[cfg(test)]
+pub mod test_utils;
 pub mod wallet;
+pub mod webhooks;
 pub mod worker;
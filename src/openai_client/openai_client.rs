@@ -8,18 +8,28 @@ use crate::{openai_client::types::Message, reputation::reputation::Reputation};
 
 use super::types::{ChatCompletion, GeneratedCaseReportSections};
 
-const OPENAI_URL: &str = "https://api.openai.com/v1/chat/completions";
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+const DEFAULT_MODEL: &str = "gpt-4o-mini-2024-07-18";
 
+/// Client of the chat completions API of OpenAI, or of any server compatible with it
 pub struct OpenAIClient {
     client: Client,
-    api_key: String,
+    completions_url: String,
+    model: String,
+    api_key: Option<String>,
 }
 
 impl OpenAIClient {
+    /// Reads `OPENAI_BASE_URL` and `OPENAI_MODEL`, defaulting to OpenAI itself, and
+    /// `OPENAI_API_KEY`, which local servers usually don't require
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
+        let base_url = env::var("OPENAI_BASE_URL").unwrap_or_else(|_| DEFAULT_BASE_URL.to_string());
         Self {
             client: Client::new(),
-            api_key: env::var("OPENAI_API_KEY").expect("OpenAI api key should be set"),
+            completions_url: format!("{}/chat/completions", base_url.trim_end_matches('/')),
+            model: env::var("OPENAI_MODEL").unwrap_or_else(|_| DEFAULT_MODEL.to_string()),
+            api_key: env::var("OPENAI_API_KEY").ok(),
         }
     }
 
@@ -27,12 +37,13 @@ impl OpenAIClient {
         &self,
         reputation: &Reputation,
     ) -> Result<GeneratedCaseReportSections> {
-        let openai_response = self
-            .client
-            .post(OPENAI_URL)
-            .bearer_auth(&self.api_key)
+        let mut request = self.client.post(&self.completions_url);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let openai_response = request
             .json(&json!({
-                "model": "gpt-4o-mini-2024-07-18",
+                "model": self.model,
                 "messages": vec![
                     Message { role: "system".to_string(), content: env::var("CASE_REPORT_PROMPT").expect("Case report prompt should be set")},
                     Message { role: "user".to_string(), content: to_string(reputation).unwrap() }
@@ -7,7 +7,8 @@ pub struct ChatCompletion {
     pub created: i64,
     pub model: String,
     pub choices: Vec<Choice>,
-    pub usage: Usage,
+    /// Not every OpenAI-compatible server reports usage
+    pub usage: Option<Usage>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -44,7 +45,7 @@ pub struct Usage {
     pub prompt_tokens: i32,
     pub completion_tokens: i32,
     pub total_tokens: i32,
-    pub completion_tokens_details: CompletionTokensDetails,
+    pub completion_tokens_details: Option<CompletionTokensDetails>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -56,11 +57,11 @@ pub struct CompletionTokensDetails {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GeneratedCaseReportSections {
-    summary: String,
-    reputation_strengths: Vec<String>,
-    reputation_challenges: Vec<String>,
-    potential_downgrade_factors: Vec<String>,
-    penalty_breakdown: Vec<String>,
+    pub summary: String,
+    pub reputation_strengths: Vec<String>,
+    pub reputation_challenges: Vec<String>,
+    pub potential_downgrade_factors: Vec<String>,
+    pub penalty_breakdown: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
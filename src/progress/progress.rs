@@ -0,0 +1,225 @@
+use std::time::Duration;
+
+use actix_web::web::Bytes;
+use futures::{stream, Stream, TryStreamExt};
+use log::error;
+use pulsar::{
+    producer, Consumer, DeserializeMessage, Error as PulsarError, SerializeMessage, TokioExecutor,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::database::{
+    models::{ReportJob, ReportJobState},
+    postgres::Database,
+};
+
+pub const WALLET_REPORT_PROGRESS_TOPIC: &str =
+    "non-persistent://public/default/wallet-report-progress";
+/// A stream is closed once no progress has been published for this long
+const PROGRESS_IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+
+pub type ProgressConsumer = Consumer<ReportProgressEvent, TokioExecutor>;
+
+/// Stages of a wallet report, each published once the stage has completed
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ReportProgressStage {
+    Queued,
+    FetchedWallet,
+    ReputationComputed,
+    CaseReportGenerated,
+    Stored,
+    Failed,
+}
+
+impl ReportProgressStage {
+    /// Last completed stage of a job in the given state
+    pub fn from_job_state(state: ReportJobState) -> Self {
+        match state {
+            ReportJobState::Queued | ReportJobState::Fetching => ReportProgressStage::Queued,
+            ReportJobState::Scoring => ReportProgressStage::FetchedWallet,
+            ReportJobState::Generating => ReportProgressStage::ReputationComputed,
+            ReportJobState::Done => ReportProgressStage::Stored,
+            ReportJobState::Failed => ReportProgressStage::Failed,
+        }
+    }
+
+    /// State a job moves into once this stage completes, if it changes
+    pub fn job_state(&self) -> Option<ReportJobState> {
+        match self {
+            ReportProgressStage::Queued => Some(ReportJobState::Queued),
+            ReportProgressStage::FetchedWallet => Some(ReportJobState::Scoring),
+            ReportProgressStage::ReputationComputed => Some(ReportJobState::Generating),
+            ReportProgressStage::CaseReportGenerated => None,
+            ReportProgressStage::Stored => Some(ReportJobState::Done),
+            ReportProgressStage::Failed => Some(ReportJobState::Failed),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReportProgressEvent {
+    pub report_id: Uuid,
+    pub stage: ReportProgressStage,
+    pub error: Option<String>,
+}
+
+impl SerializeMessage for ReportProgressEvent {
+    fn serialize_message(input: Self) -> Result<producer::Message, PulsarError> {
+        let payload = serde_json::to_vec(&input).map_err(|e| PulsarError::Custom(e.to_string()))?;
+        Ok(producer::Message {
+            payload,
+            ..Default::default()
+        })
+    }
+}
+
+impl DeserializeMessage for ReportProgressEvent {
+    type Output = Result<ReportProgressEvent, serde_json::Error>;
+
+    fn deserialize_message(payload: &pulsar::Payload) -> Self::Output {
+        serde_json::from_slice(&payload.data)
+    }
+}
+
+impl ReportProgressEvent {
+    pub fn from_job(report_job: &ReportJob) -> Self {
+        Self {
+            report_id: report_job.id,
+            stage: ReportProgressStage::from_job_state(report_job.state),
+            error: report_job.error.clone(),
+        }
+    }
+}
+
+fn sse_message(event: &str, data: &impl Serialize) -> Bytes {
+    Bytes::from(format!(
+        "event: {}\ndata: {}\n\n",
+        event,
+        serde_json::to_string(data).unwrap_or_default()
+    ))
+}
+
+enum ProgressStreamState {
+    Snapshot(ReportProgressEvent, ProgressConsumer),
+    Listening(Uuid, ProgressConsumer),
+    Stored(Uuid),
+    Finished,
+}
+
+impl ProgressStreamState {
+    fn after(event: &ReportProgressEvent, consumer: ProgressConsumer) -> Self {
+        match event.stage {
+            ReportProgressStage::Stored => ProgressStreamState::Stored(event.report_id),
+            ReportProgressStage::Failed => ProgressStreamState::Finished,
+            _ => ProgressStreamState::Listening(event.report_id, consumer),
+        }
+    }
+}
+
+async fn next_progress_event(
+    report_id: Uuid,
+    consumer: &mut ProgressConsumer,
+) -> Option<ReportProgressEvent> {
+    loop {
+        let msg = match tokio::time::timeout(PROGRESS_IDLE_TIMEOUT, consumer.try_next()).await {
+            Ok(Ok(Some(msg))) => msg,
+            Ok(Err(e)) => {
+                error!(
+                    "Failed to receive progress of report {}: {:?}",
+                    report_id, e
+                );
+                return None;
+            }
+            Ok(Ok(None)) | Err(_) => return None,
+        };
+        if let Err(e) = consumer.ack(&msg).await {
+            error!("Failed to ack progress message: {:?}", e);
+        }
+        match msg.deserialize() {
+            Ok(event) if event.report_id == report_id => return Some(event),
+            Ok(_) => continue,
+            Err(e) => error!("Couldn't deserialize progress event, error: {:?}", e),
+        }
+    }
+}
+
+/// Server-Sent Events of a report's progress, starting from the job's current state and
+/// ending with the stored `WalletReport` or the failure
+pub fn progress_stream(
+    report_job: &ReportJob,
+    consumer: ProgressConsumer,
+) -> impl Stream<Item = Result<Bytes, actix_web::Error>> {
+    let snapshot = ReportProgressEvent::from_job(report_job);
+    stream::unfold(
+        ProgressStreamState::Snapshot(snapshot, consumer),
+        |state| async move {
+            match state {
+                ProgressStreamState::Snapshot(event, consumer) => Some((
+                    Ok(sse_message("progress", &event)),
+                    ProgressStreamState::after(&event, consumer),
+                )),
+                ProgressStreamState::Listening(report_id, mut consumer) => {
+                    let event = next_progress_event(report_id, &mut consumer).await?;
+                    Some((
+                        Ok(sse_message("progress", &event)),
+                        ProgressStreamState::after(&event, consumer),
+                    ))
+                }
+                ProgressStreamState::Stored(report_id) => {
+                    let report = Database::connect()
+                        .and_then(|mut database| database.get_wallet_report(report_id));
+                    let message = match report {
+                        Ok(report) => sse_message("report", &report),
+                        Err(e) => {
+                            error!("Failed to fetch stored report {}: {:?}", report_id, e);
+                            sse_message("error", &"Unable to fetch wallet report")
+                        }
+                    };
+                    Some((Ok(message), ProgressStreamState::Finished))
+                }
+                ProgressStreamState::Finished => None,
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_job_state_round_trip() {
+        let stages = vec![
+            ReportProgressStage::Queued,
+            ReportProgressStage::FetchedWallet,
+            ReportProgressStage::ReputationComputed,
+            ReportProgressStage::Stored,
+            ReportProgressStage::Failed,
+        ];
+        for stage in stages {
+            assert_eq!(
+                ReportProgressStage::from_job_state(stage.job_state().unwrap()),
+                stage
+            );
+        }
+        assert!(ReportProgressStage::CaseReportGenerated
+            .job_state()
+            .is_none());
+    }
+
+    #[test]
+    fn test_sse_message() {
+        let event = ReportProgressEvent {
+            report_id: Uuid::nil(),
+            stage: ReportProgressStage::FetchedWallet,
+            error: None,
+        };
+        assert_eq!(
+            sse_message("progress", &event),
+            Bytes::from(
+                "event: progress\ndata: {\"report_id\":\"00000000-0000-0000-0000-000000000000\",\"stage\":\"FetchedWallet\",\"error\":null}\n\n"
+            )
+        );
+    }
+}
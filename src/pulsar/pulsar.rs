@@ -6,10 +6,14 @@ use pulsar::{
 };
 use uuid::Uuid; // Import logging macros
 
-use crate::jobs::async_jobs::WalletReportJob;
+use crate::{
+    jobs::async_jobs::WalletReportJob,
+    progress::progress::{ProgressConsumer, ReportProgressEvent},
+};
 
 const PULSAR_ADDR: &str = "pulsar://localhost:6650";
-const MAX_JOB_RETRY: usize = 3;
+/// Redeliveries of a nacked job before it is moved to the dead letter topic
+pub const MAX_JOB_RETRY: usize = 3;
 
 pub struct PulsarClient {
     internal_client: Pulsar<TokioExecutor>,
@@ -81,6 +85,21 @@ impl PulsarClient {
                 .expect("Should be able to create consumer"),
         }
     }
+
+    /// Subscribes to report progress on its own exclusive subscription, so every consumer
+    /// receives all events
+    pub async fn create_progress_consumer(&self, topic: &str) -> Result<ProgressConsumer> {
+        let id = Uuid::new_v4();
+        Ok(self
+            .internal_client
+            .consumer()
+            .with_consumer_name("CONSUMER_".to_owned() + &id.to_string())
+            .with_subscription_type(SubType::Exclusive)
+            .with_subscription("SUB_".to_owned() + &id.to_string())
+            .with_topic(topic)
+            .build()
+            .await?)
+    }
 }
 
 pub struct PulsarProducer {
@@ -94,6 +113,12 @@ impl PulsarProducer {
         info!("Enqueuing job on producer with id: {}", self.id);
         Ok(())
     }
+
+    pub async fn publish_progress(&mut self, event: ReportProgressEvent) -> Result<()> {
+        self.internal_producer.send_non_blocking(event).await?;
+        info!("Published report progress on producer with id: {}", self.id);
+        Ok(())
+    }
 }
 
 pub struct PulsarConsumer {
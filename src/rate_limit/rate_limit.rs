@@ -0,0 +1,213 @@
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER},
+    middleware::Next,
+    Error, HttpMessage, HttpResponse,
+};
+use anyhow::Result;
+use chrono::{NaiveDateTime, Utc};
+use log::{error, info};
+use serde::Serialize;
+
+use crate::{
+    auth::auth::AuthenticatedUser,
+    database::{
+        models::{UsageCounterKind, User},
+        postgres::Database,
+    },
+};
+
+const REQUESTS_HEADERS: [&str; 3] = [
+    "x-ratelimit-limit",
+    "x-ratelimit-remaining",
+    "x-ratelimit-reset",
+];
+const REPORTS_HEADERS: [&str; 3] = [
+    "x-ratelimit-reports-limit",
+    "x-ratelimit-reports-remaining",
+    "x-ratelimit-reports-reset",
+];
+
+/// A user's consumption of one of their quotas in the current window
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RateLimit {
+    pub kind: UsageCounterKind,
+    pub limit: i32,
+    pub used: i32,
+    pub remaining: i32,
+    pub reset: NaiveDateTime,
+}
+
+impl RateLimit {
+    pub fn new(kind: UsageCounterKind, limit: i32, used: i32, now: NaiveDateTime) -> Self {
+        Self {
+            kind,
+            limit,
+            used,
+            remaining: (limit - used).max(0),
+            reset: kind.window_start(now) + kind.window(),
+        }
+    }
+
+    pub fn exceeded(&self) -> bool {
+        self.used > self.limit
+    }
+
+    /// Adds the `X-RateLimit-*` headers, with the reset given in seconds from `now`
+    pub fn apply_headers(&self, headers: &mut HeaderMap, now: NaiveDateTime) {
+        let [limit, remaining, reset] = match self.kind {
+            UsageCounterKind::Requests => REQUESTS_HEADERS,
+            UsageCounterKind::Reports => REPORTS_HEADERS,
+        };
+        let reset_seconds = (self.reset - now).num_seconds().max(0);
+        headers.insert(
+            HeaderName::from_static(limit),
+            HeaderValue::from(self.limit),
+        );
+        headers.insert(
+            HeaderName::from_static(remaining),
+            HeaderValue::from(self.remaining),
+        );
+        headers.insert(
+            HeaderName::from_static(reset),
+            HeaderValue::from(reset_seconds),
+        );
+        if self.exceeded() {
+            headers.insert(RETRY_AFTER, HeaderValue::from(reset_seconds));
+        }
+    }
+
+    /// `429 Too Many Requests` response carrying the rate limit headers
+    pub fn exceeded_response(&self, now: NaiveDateTime) -> HttpResponse {
+        let mut response = HttpResponse::TooManyRequests().json(match self.kind {
+            UsageCounterKind::Requests => "Request rate limit exceeded",
+            UsageCounterKind::Reports => "Daily wallet report quota exceeded",
+        });
+        self.apply_headers(response.headers_mut(), now);
+        response
+    }
+}
+
+/// Counts one use of the user's quota and returns the resulting rate limit
+pub fn consume_quota(user: &User, kind: UsageCounterKind, now: NaiveDateTime) -> Result<RateLimit> {
+    let mut database = Database::connect()?;
+    let used = database.increment_usage(user.id, kind, kind.window_start(now))?;
+    Ok(RateLimit::new(kind, user.quota(kind), used, now))
+}
+
+/// Counts one use of the user's quota unless it's used up, returning an exceeded rate limit
+/// without counting in that case. For uses which may still be taken back with `release_quota`
+pub fn consume_quota_within_limit(
+    user: &User,
+    kind: UsageCounterKind,
+    now: NaiveDateTime,
+) -> Result<RateLimit> {
+    let mut database = Database::connect()?;
+    let limit = user.quota(kind);
+    let used =
+        database.increment_usage_within_limit(user.id, kind, kind.window_start(now), limit)?;
+    Ok(RateLimit::new(kind, limit, used.unwrap_or(limit + 1), now))
+}
+
+/// Gives back a use counted at `now` which didn't go through
+pub fn release_quota(user: &User, kind: UsageCounterKind, now: NaiveDateTime) -> Result<()> {
+    let mut database = Database::connect()?;
+    database.decrement_usage(user.id, kind, kind.window_start(now))
+}
+
+pub fn get_quota_usage(
+    user: &User,
+    kind: UsageCounterKind,
+    now: NaiveDateTime,
+) -> Result<RateLimit> {
+    let mut database = Database::connect()?;
+    let used = database.get_usage(user.id, kind, kind.window_start(now))?;
+    Ok(RateLimit::new(kind, user.quota(kind), used, now))
+}
+
+/// Limits the requests per minute of authenticated users, must run after `require_api_key`
+pub async fn enforce_rate_limit(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let authenticated_user = req.extensions().get::<AuthenticatedUser>().cloned();
+    let Some(AuthenticatedUser(user, _)) = authenticated_user else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+
+    let now = Utc::now().naive_local();
+    let rate_limit = match consume_quota(&user, UsageCounterKind::Requests, now) {
+        Ok(rate_limit) => rate_limit,
+        Err(err) => {
+            // usage tracking being unavailable shouldn't take the whole API down
+            error!("Failed to count request of user {}: {:?}", user.id, err);
+            return Ok(next.call(req).await?.map_into_boxed_body());
+        }
+    };
+    if rate_limit.exceeded() {
+        info!("User {} exceeded their request rate limit", user.id);
+        return Ok(req.into_response(rate_limit.exceeded_response(now)));
+    }
+
+    let mut response = next.call(req).await?.map_into_boxed_body();
+    rate_limit.apply_headers(response.headers_mut(), now);
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn mock_now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 2, 10)
+            .unwrap()
+            .and_hms_opt(13, 45, 30)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_window_start() {
+        let now = mock_now();
+        assert_eq!(
+            UsageCounterKind::Requests.window_start(now),
+            now.date().and_hms_opt(13, 45, 0).unwrap()
+        );
+        assert_eq!(
+            UsageCounterKind::Reports.window_start(now),
+            now.date().and_hms_opt(0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let now = mock_now();
+        let rate_limit = RateLimit::new(UsageCounterKind::Requests, 60, 60, now);
+        assert_eq!(rate_limit.remaining, 0);
+        assert!(!rate_limit.exceeded());
+
+        let rate_limit = RateLimit::new(UsageCounterKind::Requests, 60, 61, now);
+        assert_eq!(rate_limit.remaining, 0);
+        assert!(rate_limit.exceeded());
+    }
+
+    #[test]
+    fn test_exceeded_response_headers() {
+        let now = mock_now();
+        let response =
+            RateLimit::new(UsageCounterKind::Reports, 20, 21, now).exceeded_response(now);
+        let headers = response.headers();
+
+        assert_eq!(
+            response.status(),
+            actix_web::http::StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(headers.get("X-RateLimit-Reports-Limit").unwrap(), "20");
+        assert_eq!(headers.get("X-RateLimit-Reports-Remaining").unwrap(), "0");
+        // 10:14:30 left until midnight
+        assert_eq!(headers.get("X-RateLimit-Reports-Reset").unwrap(), "36870");
+        assert_eq!(headers.get(RETRY_AFTER).unwrap(), "36870");
+    }
+}
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use serde::Serialize;
 use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
 
 use crate::database::{
@@ -6,6 +7,13 @@ use crate::database::{
     postgres::Database,
 };
 
+use super::{
+    indicator_registry::{Indicator, IndicatorContext},
+    reputation::ReputationPenalty,
+    scoring_policy::ScoringPolicy,
+};
+
+#[derive(Serialize, Clone, Debug)]
 pub struct KnownDiscreditedAssociates {
     pub wallets: Vec<KnownDiscreditedWallet>,
 }
@@ -20,6 +28,19 @@ impl KnownDiscreditedAssociates {
     }
 }
 
+impl Indicator for KnownDiscreditedAssociates {
+    const NAME: &'static str = "discredited_associates";
+
+    fn from_context(context: &IndicatorContext) -> Self {
+        context.discredited_associates.clone()
+    }
+
+    fn penalty(&self, policy: &ScoringPolicy) -> ReputationPenalty {
+        (self, policy).into()
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
 pub struct KnownCreditedAssociates {
     pub wallets: Vec<KnownCreditedWallet>,
 }
@@ -34,14 +55,30 @@ impl KnownCreditedAssociates {
     }
 }
 
+impl Indicator for KnownCreditedAssociates {
+    const NAME: &'static str = "credited_associates";
+
+    fn from_context(context: &IndicatorContext) -> Self {
+        context.credited_associates.clone()
+    }
+
+    fn penalty(&self, policy: &ScoringPolicy) -> ReputationPenalty {
+        (self, policy).into()
+    }
+}
+
 pub struct KnownAssociates {
     pub wallets: Vec<String>,
 }
 
 impl KnownAssociates {
-    pub fn new(transactions: Vec<EncodedConfirmedTransactionWithStatusMeta>) -> Result<Self> {
-        let wallets: Vec<String> = transactions
-            .into_iter()
+    /// Collects the unique counterparties of `wallet_addr`, excluding the wallet itself
+    pub fn new(
+        wallet_addr: &str,
+        transactions: &[EncodedConfirmedTransactionWithStatusMeta],
+    ) -> Result<Self> {
+        let mut wallets: Vec<String> = transactions
+            .iter()
             .flat_map(|tx| {
                 tx.transaction
                     .transaction
@@ -55,7 +92,10 @@ impl KnownAssociates {
                             .collect()
                     })
             })
+            .filter(|addr| addr != wallet_addr)
             .collect();
+        wallets.sort();
+        wallets.dedup();
 
         Ok(Self { wallets })
     }
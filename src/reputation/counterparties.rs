@@ -0,0 +1,304 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDateTime};
+use serde::Serialize;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+
+use crate::database::{
+    models::{CounterpartyDirection, CounterpartyEdge},
+    postgres::Database,
+};
+
+use super::funding_source::account_keys;
+
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+/// Hops from the wallet a neighbourhood can reach, exchanges make larger ones explode
+pub const MAX_GRAPH_DEPTH: usize = 2;
+/// Edges fetched per hop, the highest volume ones first
+const MAX_EDGES_PER_HOP: i64 = 500;
+
+/// Aggregates the SOL moved between the wallet and each counterparty over the transactions.
+/// A counterparty is any account whose balance moved opposite to the wallet's, so programs
+/// and accounts only read by a transaction aren't counterparties
+pub fn counterparty_edges(
+    wallet_addr: &str,
+    transactions: &[EncodedConfirmedTransactionWithStatusMeta],
+) -> Vec<CounterpartyEdge> {
+    let mut edges: HashMap<(String, CounterpartyDirection), CounterpartyEdge> = HashMap::new();
+
+    for transaction in transactions {
+        let (Some(meta), Some(keys), Some(seen_at)) = (
+            transaction.transaction.meta.as_ref(),
+            account_keys(transaction),
+            transaction
+                .block_time
+                .and_then(|block_time| DateTime::from_timestamp(block_time, 0))
+                .map(|block_time| block_time.naive_utc()),
+        ) else {
+            continue;
+        };
+        let balance_changes: Vec<(String, i128)> = keys
+            .into_iter()
+            .zip(meta.pre_balances.iter().zip(&meta.post_balances))
+            .map(|(key, (pre, post))| (key, *post as i128 - *pre as i128))
+            .collect();
+        let Some(wallet_change) = balance_changes
+            .iter()
+            .find(|(key, _)| key == wallet_addr)
+            .map(|(_, change)| *change)
+        else {
+            continue;
+        };
+        let direction = match wallet_change {
+            change if change > 0 => CounterpartyDirection::Incoming,
+            change if change < 0 => CounterpartyDirection::Outgoing,
+            _ => continue,
+        };
+
+        for (counterparty, change) in balance_changes {
+            if counterparty == wallet_addr || change.signum() != -wallet_change.signum() {
+                continue;
+            }
+            let volume = change.abs().min(wallet_change.abs()) as i64;
+            edges
+                .entry((counterparty.clone(), direction))
+                .and_modify(|edge| {
+                    edge.volume_lamports += volume;
+                    edge.tx_count += 1;
+                    edge.first_seen_at = edge.first_seen_at.min(seen_at);
+                    edge.last_seen_at = edge.last_seen_at.max(seen_at);
+                })
+                .or_insert_with(|| CounterpartyEdge {
+                    wallet_addr: wallet_addr.to_string(),
+                    counterparty_addr: counterparty,
+                    direction,
+                    volume_lamports: volume,
+                    tx_count: 1,
+                    first_seen_at: seen_at,
+                    last_seen_at: seen_at,
+                });
+        }
+    }
+
+    let mut edges: Vec<CounterpartyEdge> = edges.into_values().collect();
+    edges.sort_by_key(|edge| std::cmp::Reverse(edge.volume_lamports));
+    edges
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct GraphNode {
+    pub id: String,
+    pub known_discredited: bool,
+    pub known_credited: bool,
+}
+
+/// An edge pointing in the direction the SOL moved
+#[derive(Serialize, Debug, PartialEq)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub volume_sol: f64,
+    pub tx_count: i64,
+    pub first_seen_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+}
+
+/// A wallet's neighbourhood of counterparties in the node/edge shape graph libraries expect
+#[derive(Serialize, Debug)]
+pub struct CounterpartyGraph {
+    pub wallet_addr: String,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl CounterpartyGraph {
+    pub fn from_edges(
+        wallet_addr: &str,
+        edges: &[CounterpartyEdge],
+        discredited: &HashSet<String>,
+        credited: &HashSet<String>,
+    ) -> Self {
+        let addrs: BTreeSet<&str> = std::iter::once(wallet_addr)
+            .chain(
+                edges
+                    .iter()
+                    .flat_map(|edge| [edge.wallet_addr.as_str(), edge.counterparty_addr.as_str()]),
+            )
+            .collect();
+        let nodes = addrs
+            .into_iter()
+            .map(|addr| GraphNode {
+                id: addr.to_string(),
+                known_discredited: discredited.contains(addr),
+                known_credited: credited.contains(addr),
+            })
+            .collect();
+        let edges = edges
+            .iter()
+            .map(|edge| {
+                let (source, target) = match edge.direction {
+                    CounterpartyDirection::Incoming => (&edge.counterparty_addr, &edge.wallet_addr),
+                    CounterpartyDirection::Outgoing => (&edge.wallet_addr, &edge.counterparty_addr),
+                };
+                GraphEdge {
+                    source: source.clone(),
+                    target: target.clone(),
+                    volume_sol: edge.volume_lamports as f64 / LAMPORTS_PER_SOL,
+                    tx_count: edge.tx_count,
+                    first_seen_at: edge.first_seen_at,
+                    last_seen_at: edge.last_seen_at,
+                }
+            })
+            .collect();
+
+        Self {
+            wallet_addr: wallet_addr.to_string(),
+            nodes,
+            edges,
+        }
+    }
+
+    /// Expands the stored edges outwards from the wallet, `depth` hops deep
+    pub fn load(database: &mut Database, wallet_addr: &str, depth: usize) -> Result<Self> {
+        let mut visited = HashSet::from([wallet_addr.to_string()]);
+        let mut frontier = vec![wallet_addr.to_string()];
+        let mut seen_edges = HashSet::new();
+        let mut edges = Vec::new();
+
+        for _ in 0..depth.clamp(1, MAX_GRAPH_DEPTH) {
+            if frontier.is_empty() {
+                break;
+            }
+            let hop_edges = database.get_counterparty_edges(&frontier, MAX_EDGES_PER_HOP)?;
+            frontier = Vec::new();
+            for edge in hop_edges {
+                for addr in [&edge.wallet_addr, &edge.counterparty_addr] {
+                    if visited.insert(addr.clone()) {
+                        frontier.push(addr.clone());
+                    }
+                }
+                let key = (
+                    edge.wallet_addr.clone(),
+                    edge.counterparty_addr.clone(),
+                    edge.direction,
+                );
+                if seen_edges.insert(key) {
+                    edges.push(edge);
+                }
+            }
+        }
+
+        let addrs: Vec<String> = visited.into_iter().collect();
+        let discredited = database
+            .find_discredited_associates(&addrs)?
+            .into_iter()
+            .map(|wallet| wallet.wallet_addr)
+            .collect();
+        let credited = database
+            .find_credited_associates(&addrs)?
+            .into_iter()
+            .map(|wallet| wallet.wallet_addr)
+            .collect();
+        Ok(Self::from_edges(
+            wallet_addr,
+            &edges,
+            &discredited,
+            &credited,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::test_utils::create_mock_transaction;
+
+    use super::*;
+
+    fn create_mock_edge(
+        counterparty_addr: &str,
+        direction: CounterpartyDirection,
+        volume_lamports: i64,
+    ) -> CounterpartyEdge {
+        CounterpartyEdge {
+            wallet_addr: "wallet".to_string(),
+            counterparty_addr: counterparty_addr.to_string(),
+            direction,
+            volume_lamports,
+            tx_count: 1,
+            first_seen_at: NaiveDateTime::default(),
+            last_seen_at: NaiveDateTime::default(),
+        }
+    }
+
+    #[test]
+    fn test_counterparty_edges() {
+        let program = "11111111111111111111111111111111";
+        let transactions = vec![
+            create_mock_transaction(
+                "signature",
+                &["funder", "wallet", program],
+                &[10_000_000_000, 0, 1],
+                &[7_999_995_000, 2_000_000_000, 1],
+                1_600_000_000,
+            ),
+            create_mock_transaction(
+                "signature",
+                &["wallet", "receiver", program],
+                &[2_000_000_000, 0, 1],
+                &[1_499_995_000, 500_000_000, 1],
+                1_600_000_100,
+            ),
+            create_mock_transaction(
+                "signature",
+                &["wallet", "receiver", program],
+                &[1_499_995_000, 500_000_000, 1],
+                &[1_249_990_000, 750_000_000, 1],
+                1_600_000_200,
+            ),
+        ];
+
+        let edges = counterparty_edges("wallet", &transactions);
+        assert_eq!(edges.len(), 2);
+        assert_eq!(edges[0].counterparty_addr, "funder");
+        assert_eq!(edges[0].direction, CounterpartyDirection::Incoming);
+        assert_eq!(edges[0].volume_lamports, 2_000_000_000);
+        assert_eq!(edges[1].counterparty_addr, "receiver");
+        assert_eq!(edges[1].direction, CounterpartyDirection::Outgoing);
+        assert_eq!(edges[1].volume_lamports, 750_000_000);
+        assert_eq!(edges[1].tx_count, 2);
+        assert!(edges[1].first_seen_at < edges[1].last_seen_at);
+    }
+
+    #[test]
+    fn test_graph_from_edges() {
+        let edges = vec![
+            create_mock_edge("funder", CounterpartyDirection::Incoming, 2_000_000_000),
+            create_mock_edge("receiver", CounterpartyDirection::Outgoing, 500_000_000),
+        ];
+        let graph = CounterpartyGraph::from_edges(
+            "wallet",
+            &edges,
+            &HashSet::from(["funder".to_string()]),
+            &HashSet::new(),
+        );
+
+        let ids: Vec<&str> = graph.nodes.iter().map(|node| node.id.as_str()).collect();
+        assert_eq!(ids, vec!["funder", "receiver", "wallet"]);
+        assert!(graph.nodes[0].known_discredited);
+        assert!(!graph.nodes[1].known_discredited);
+
+        assert_eq!(graph.edges[0].source, "funder");
+        assert_eq!(graph.edges[0].target, "wallet");
+        assert_eq!(graph.edges[0].volume_sol, 2.0);
+        assert_eq!(graph.edges[1].source, "wallet");
+        assert_eq!(graph.edges[1].target, "receiver");
+    }
+
+    #[test]
+    fn test_graph_without_edges_has_wallet_node() {
+        let graph = CounterpartyGraph::from_edges("wallet", &[], &HashSet::new(), &HashSet::new());
+        assert_eq!(graph.nodes.len(), 1);
+        assert!(graph.edges.is_empty());
+    }
+}
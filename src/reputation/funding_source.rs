@@ -0,0 +1,425 @@
+use std::{collections::HashSet, str::FromStr};
+
+use anyhow::Result;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiMessage,
+};
+
+use crate::{
+    database::postgres::Database,
+    solana_client::chain_data::{transaction_signature, ChainDataSource},
+    wallet::wallet::Wallet,
+};
+
+use super::{
+    indicator_registry::{Indicator, IndicatorContext},
+    reputation::ReputationPenalty,
+    scoring_policy::ScoringPolicy,
+};
+
+/// Transactions fetched at a time while scanning an address's first transactions
+const FUNDING_SCAN_BATCH_SIZE: usize = 25;
+/// First transactions scanned for an inbound transfer
+const MAX_FUNDING_SCAN_TRANSACTIONS: usize = 100;
+
+/// A single inbound SOL transfer funding a wallet
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FundingHop {
+    pub funder: String,
+    pub funded: String,
+    pub signature: String,
+    pub lamports: u64,
+    pub block_time: Option<i64>,
+}
+
+/// Who funded a wallet, followed back through the funders' own funding
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct FundingSource {
+    /// Funding transfers, starting with the one into the wallet itself
+    pub path: Vec<FundingHop>,
+    /// Number of hops to the first known discredited funder on the path
+    pub discredited_funder_hops: Option<usize>,
+    /// Label of the exchange the path ends at
+    pub exchange: Option<String>,
+}
+
+impl Indicator for FundingSource {
+    const NAME: &'static str = "funding_source";
+
+    fn from_context(context: &IndicatorContext) -> Self {
+        context.funding_source.clone()
+    }
+
+    fn penalty(&self, policy: &ScoringPolicy) -> ReputationPenalty {
+        (self, policy).into()
+    }
+}
+
+/// Account keys of the transaction in the order of its balances
+pub fn account_keys(
+    transaction: &EncodedConfirmedTransactionWithStatusMeta,
+) -> Option<Vec<String>> {
+    match &transaction.transaction.transaction {
+        EncodedTransaction::Json(ui_transaction) => match &ui_transaction.message {
+            UiMessage::Raw(message) => Some(message.account_keys.clone()),
+            UiMessage::Parsed(message) => Some(
+                message
+                    .account_keys
+                    .iter()
+                    .map(|account| account.pubkey.clone())
+                    .collect(),
+            ),
+        },
+        encoded => encoded.decode().map(|versioned_tx| {
+            versioned_tx
+                .message
+                .static_account_keys()
+                .iter()
+                .map(|key| key.to_string())
+                .collect()
+        }),
+    }
+}
+
+impl FundingHop {
+    /// The funder is the account whose balance dropped the most while the funded wallet's
+    /// balance grew, which covers plain transfers as well as account creation
+    pub fn from_balances(
+        funded: &str,
+        signature: &str,
+        block_time: Option<i64>,
+        account_keys: &[String],
+        pre_balances: &[u64],
+        post_balances: &[u64],
+    ) -> Option<Self> {
+        let balance_changes: Vec<(&String, i128)> = account_keys
+            .iter()
+            .zip(pre_balances.iter().zip(post_balances))
+            .map(|(key, (pre, post))| (key, *post as i128 - *pre as i128))
+            .collect();
+        let received = balance_changes
+            .iter()
+            .find(|(key, _)| key.as_str() == funded)
+            .map(|(_, change)| *change)
+            .filter(|change| *change > 0)?;
+        let (funder, _) = balance_changes
+            .iter()
+            .filter(|(key, change)| key.as_str() != funded && *change < 0)
+            .min_by_key(|(_, change)| *change)?;
+
+        Some(Self {
+            funder: funder.to_string(),
+            funded: funded.to_string(),
+            signature: signature.to_string(),
+            lamports: received as u64,
+            block_time,
+        })
+    }
+
+    fn from_transaction(
+        funded: &str,
+        signature: &str,
+        transaction: &EncodedConfirmedTransactionWithStatusMeta,
+    ) -> Option<Self> {
+        let meta = transaction.transaction.meta.as_ref()?;
+        Self::from_balances(
+            funded,
+            signature,
+            transaction.block_time,
+            &account_keys(transaction)?,
+            &meta.pre_balances,
+            &meta.post_balances,
+        )
+    }
+}
+
+/// Scans the address's oldest transactions forward for the first one increasing its balance.
+/// `None` if there is none among them or its history is longer than `max_signature_pages`
+async fn first_inbound_transfer(
+    chain_data: &dyn ChainDataSource,
+    funded: &str,
+    max_signature_pages: usize,
+) -> Result<Option<FundingHop>> {
+    let pub_key = Pubkey::from_str(funded)?;
+    let Some(mut signatures) = chain_data
+        .get_first_signatures(&pub_key, max_signature_pages)
+        .await?
+    else {
+        return Ok(None);
+    };
+    signatures.truncate(MAX_FUNDING_SCAN_TRANSACTIONS);
+
+    for batch in signatures.chunks(FUNDING_SCAN_BATCH_SIZE) {
+        let transactions = chain_data.batch_process_transactions(batch.to_vec()).await;
+        let hop = transactions.iter().find_map(|transaction| {
+            FundingHop::from_transaction(funded, &transaction_signature(transaction)?, transaction)
+        });
+        if hop.is_some() {
+            return Ok(hop);
+        }
+    }
+    Ok(None)
+}
+
+impl FundingSource {
+    /// Follows the first inbound transfer into the wallet back through its funders, up to the
+    /// policy's hop limit. Tracing stops at a known discredited wallet or a labeled exchange,
+    /// at an address whose history is too long to reach its first transactions, and on errors,
+    /// as a partial path is still worth reporting
+    pub async fn trace(
+        chain_data: &dyn ChainDataSource,
+        database: &mut Database,
+        wallet: &Wallet,
+        policy: &ScoringPolicy,
+    ) -> Self {
+        Self::trace_with(chain_data, &wallet.wallet_addr, policy, |funder| {
+            Ok(!database
+                .find_discredited_associates(&[funder.to_string()])?
+                .is_empty())
+        })
+        .await
+    }
+
+    async fn trace_with(
+        chain_data: &dyn ChainDataSource,
+        wallet_addr: &str,
+        policy: &ScoringPolicy,
+        mut is_discredited: impl FnMut(&str) -> Result<bool>,
+    ) -> Self {
+        let thresholds = &policy.funding_source;
+        let mut funding_source = Self::default();
+        let mut funded = wallet_addr.to_string();
+        let mut visited = HashSet::from([funded.clone()]);
+
+        while funding_source.path.len() < thresholds.max_hops {
+            let hop =
+                match first_inbound_transfer(chain_data, &funded, thresholds.max_signature_pages)
+                    .await
+                {
+                    Ok(Some(hop)) => hop,
+                    Ok(None) => break,
+                    Err(e) => {
+                        warn!(
+                            "Stopped tracing funding of wallet {} at {}: {:?}",
+                            wallet_addr, funded, e
+                        );
+                        break;
+                    }
+                };
+            let funder = hop.funder.clone();
+            funding_source.path.push(hop);
+
+            if let Some(label) = thresholds.exchanges.get(&funder) {
+                funding_source.exchange = Some(label.clone());
+                break;
+            }
+            match is_discredited(&funder) {
+                Ok(true) => {
+                    funding_source.discredited_funder_hops = Some(funding_source.path.len());
+                    break;
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    warn!(
+                        "Stopped tracing funding of wallet {} at {}: {:?}",
+                        wallet_addr, funder, e
+                    );
+                    break;
+                }
+            }
+            if !visited.insert(funder.clone()) {
+                break;
+            }
+            funded = funder;
+        }
+
+        info!(
+            "Traced {} funding hops for wallet: {}",
+            funding_source.path.len(),
+            wallet_addr
+        );
+        funding_source
+    }
+
+    /// Funders from the wallet back to the earliest traced one, e.g. `wallet <- a <- b`
+    pub fn describe_path(&self) -> Option<String> {
+        let funded = self.path.first()?.funded.clone();
+        Some(
+            std::iter::once(funded)
+                .chain(self.path.iter().map(|hop| hop.funder.clone()))
+                .collect::<Vec<_>>()
+                .join(" <- "),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::Path};
+
+    use solana_client::rpc_response::RpcConfirmedTransactionStatusWithSignature;
+    use uuid::Uuid;
+
+    use crate::{
+        solana_client::chain_data::{
+            write_fixture, FixtureDataSource, FIRST_SIGNATURES, TRANSACTION,
+        },
+        test_utils::test_utils::{create_mock_signature, create_mock_transaction},
+    };
+
+    use super::*;
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    #[test]
+    fn test_funding_hop_from_balances() {
+        let hop = FundingHop::from_balances(
+            "wallet",
+            "signature",
+            Some(1_600_000_000),
+            &keys(&["funder", "wallet", "11111111111111111111111111111111"]),
+            &[10_000_000_000, 0, 1],
+            &[8_999_995_000, 1_000_000_000, 1],
+        )
+        .unwrap();
+        assert_eq!(hop.funder, "funder");
+        assert_eq!(hop.lamports, 1_000_000_000);
+
+        // an outbound transfer doesn't fund the wallet
+        assert!(FundingHop::from_balances(
+            "wallet",
+            "signature",
+            None,
+            &keys(&["wallet", "receiver"]),
+            &[1_000_000_000, 0],
+            &[499_995_000, 500_000_000],
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_describe_path() {
+        assert_eq!(FundingSource::default().describe_path(), None);
+
+        let hop = |funder: &str, funded: &str| FundingHop {
+            funder: funder.to_string(),
+            funded: funded.to_string(),
+            signature: String::new(),
+            lamports: 1,
+            block_time: None,
+        };
+        let funding_source = FundingSource {
+            path: vec![hop("a", "wallet"), hop("b", "a")],
+            discredited_funder_hops: None,
+            exchange: None,
+        };
+        assert_eq!(funding_source.describe_path().unwrap(), "wallet <- a <- b");
+    }
+
+    fn write_transaction_fixture(
+        dir: &Path,
+        signature: &str,
+        account_keys: &[&str],
+        pre_balances: &[u64],
+        post_balances: &[u64],
+    ) {
+        let transaction = create_mock_transaction(
+            signature,
+            account_keys,
+            pre_balances,
+            post_balances,
+            1_600_000_000,
+        );
+        write_fixture(dir, TRANSACTION, signature, &transaction).unwrap();
+    }
+
+    fn write_first_signatures_fixture(dir: &Path, addr: &str, signatures: &[&str]) {
+        let signatures: Vec<_> = signatures
+            .iter()
+            .map(|signature| create_mock_signature(signature, Some(1_600_000_000)))
+            .collect();
+        write_fixture(dir, FIRST_SIGNATURES, addr, &Some(signatures)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_trace() {
+        let dir = env::temp_dir().join(format!("funding-source-{}", Uuid::new_v4()));
+        let program = "11111111111111111111111111111111";
+        let wallet = Pubkey::new_unique().to_string();
+        let funder = Pubkey::new_unique().to_string();
+        let discredited = Pubkey::new_unique().to_string();
+        let receiver = Pubkey::new_unique().to_string();
+
+        // the wallet's first transaction sends SOL out, the second one funds it
+        write_first_signatures_fixture(&dir, &wallet, &["wallet_out", "wallet_in"]);
+        write_transaction_fixture(
+            &dir,
+            "wallet_out",
+            &[&wallet, &receiver, program],
+            &[1_000_000, 0, 1],
+            &[495_000, 500_000, 1],
+        );
+        write_transaction_fixture(
+            &dir,
+            "wallet_in",
+            &[&funder, &wallet, program],
+            &[10_000_000_000, 495_000, 1],
+            &[8_999_995_000, 1_000_495_000, 1],
+        );
+        write_first_signatures_fixture(&dir, &funder, &["funder_in"]);
+        write_transaction_fixture(
+            &dir,
+            "funder_in",
+            &[&discredited, &funder, program],
+            &[50_000_000_000, 0, 1],
+            &[39_999_995_000, 10_000_000_000, 1],
+        );
+        let source = FixtureDataSource::new(dir.clone());
+        let policy = ScoringPolicy::default();
+
+        let funding_source =
+            FundingSource::trace_with(&source, &wallet, &policy, |addr| Ok(addr == discredited))
+                .await;
+        assert_eq!(funding_source.path.len(), 2);
+        assert_eq!(funding_source.path[0].funder, funder);
+        assert_eq!(funding_source.path[0].signature, "wallet_in");
+        assert_eq!(funding_source.path[0].lamports, 1_000_000_000);
+        assert_eq!(funding_source.path[1].funder, discredited);
+        assert_eq!(funding_source.discredited_funder_hops, Some(2));
+
+        // the discredited wallet's history has no fixture, so tracing further fails and
+        // stops with the path traced so far instead of an error
+        let funding_source =
+            FundingSource::trace_with(&source, &wallet, &policy, |_| Ok(false)).await;
+        assert_eq!(funding_source.path.len(), 2);
+        assert_eq!(funding_source.discredited_funder_hops, None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_trace_stops_at_long_history() {
+        let dir = env::temp_dir().join(format!("funding-source-{}", Uuid::new_v4()));
+        let wallet = Pubkey::new_unique().to_string();
+        write_fixture(
+            &dir,
+            FIRST_SIGNATURES,
+            &wallet,
+            &None::<Vec<RpcConfirmedTransactionStatusWithSignature>>,
+        )
+        .unwrap();
+        let source = FixtureDataSource::new(dir.clone());
+
+        let funding_source =
+            FundingSource::trace_with(&source, &wallet, &ScoringPolicy::default(), |_| Ok(false))
+                .await;
+        assert!(funding_source.path.is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
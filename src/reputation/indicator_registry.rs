@@ -0,0 +1,205 @@
+use std::fmt::Debug;
+
+use log::{debug, info, warn};
+use serde::Serialize;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+
+use crate::wallet::wallet::Wallet;
+
+use super::{
+    associates::{KnownCreditedAssociates, KnownDiscreditedAssociates},
+    funding_source::FundingSource,
+    indicators::{
+        AvgPrioritizationFee, DaysSinceLastBlock, PrioritizationFeeStdDeviation, TokenPortfolio,
+        TransactionFailureRate, TransactionsWithNewWallets, TxPerHour, WalletAge, WalletBalance,
+        WalletBalanceVolatility, WalletRewards,
+    },
+    reputation::ReputationPenalty,
+    scoring_policy::ScoringPolicy,
+};
+
+/// Everything an indicator can draw on when evaluating a wallet
+pub struct IndicatorContext<'a> {
+    pub wallet: &'a Wallet,
+    pub confirmed_transactions: &'a [EncodedConfirmedTransactionWithStatusMeta],
+    pub discredited_associates: &'a KnownDiscreditedAssociates,
+    pub credited_associates: &'a KnownCreditedAssociates,
+    pub funding_source: &'a FundingSource,
+    pub policy: &'a ScoringPolicy,
+}
+
+/// A reputational metric computed from a wallet, along with the penalty it incurs
+pub trait Indicator: Serialize + Debug + Sized {
+    /// Unique name, used as the metric key in reports and to disable the indicator
+    const NAME: &'static str;
+
+    fn from_context(context: &IndicatorContext) -> Self;
+
+    fn penalty(&self, policy: &ScoringPolicy) -> ReputationPenalty;
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct IndicatorResult {
+    pub name: String,
+    pub metric: serde_json::Value,
+    pub penalty: ReputationPenalty,
+    pub weight: f64,
+    /// Points this indicator added to (or removed from) the rating score
+    pub contribution: i32,
+}
+
+fn evaluate<T: Indicator>(context: &IndicatorContext) -> IndicatorResult {
+    let indicator = T::from_context(context);
+    debug!("Computed {}: {:?}", T::NAME, indicator);
+    let penalty = indicator.penalty(context.policy);
+    IndicatorResult {
+        name: T::NAME.to_string(),
+        metric: serde_json::to_value(&indicator).unwrap_or_default(),
+        weight: context.policy.weight(T::NAME),
+        contribution: context.policy.contribution(T::NAME, penalty.severity),
+        penalty,
+    }
+}
+
+struct RegisteredIndicator {
+    name: &'static str,
+    evaluate: fn(&IndicatorContext) -> IndicatorResult,
+}
+
+/// The set of indicators a wallet is evaluated against, in report order
+pub struct IndicatorRegistry {
+    indicators: Vec<RegisteredIndicator>,
+}
+
+impl Default for IndicatorRegistry {
+    fn default() -> Self {
+        Self::empty()
+            .register::<TxPerHour>()
+            .register::<WalletBalance>()
+            .register::<DaysSinceLastBlock>()
+            .register::<TransactionFailureRate>()
+            .register::<AvgPrioritizationFee>()
+            .register::<PrioritizationFeeStdDeviation>()
+            .register::<WalletBalanceVolatility>()
+            .register::<TransactionsWithNewWallets>()
+            .register::<TokenPortfolio>()
+            .register::<WalletAge>()
+            .register::<FundingSource>()
+            .register::<KnownDiscreditedAssociates>()
+            .register::<KnownCreditedAssociates>()
+    }
+}
+
+impl IndicatorRegistry {
+    pub fn empty() -> Self {
+        Self {
+            indicators: Vec::new(),
+        }
+    }
+
+    /// All default indicators plus the optional ones enabled by the scoring policy, minus those
+    /// it disables
+    pub fn from_policy(policy: &ScoringPolicy) -> Self {
+        let mut registry = Self::default();
+        for name in &policy.enabled_indicators {
+            registry = match name.as_str() {
+                WalletRewards::NAME => registry.register::<WalletRewards>(),
+                _ => {
+                    warn!("Ignoring unknown optional indicator: {}", name);
+                    registry
+                }
+            };
+        }
+        for name in &policy.disabled_indicators {
+            registry.disable(name);
+        }
+        registry
+    }
+
+    pub fn register<T: Indicator>(mut self) -> Self {
+        self.disable(T::NAME);
+        self.indicators.push(RegisteredIndicator {
+            name: T::NAME,
+            evaluate: evaluate::<T>,
+        });
+        self
+    }
+
+    pub fn disable(&mut self, name: &str) {
+        if self.is_enabled(name) {
+            info!("Disabling indicator: {}", name);
+        }
+        self.indicators.retain(|indicator| indicator.name != name);
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.indicators
+            .iter()
+            .any(|indicator| indicator.name == name)
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.indicators
+            .iter()
+            .map(|indicator| indicator.name)
+            .collect()
+    }
+
+    pub fn evaluate(&self, context: &IndicatorContext) -> Vec<IndicatorResult> {
+        self.indicators
+            .iter()
+            .map(|indicator| (indicator.evaluate)(context))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_disable() {
+        let policy = ScoringPolicy {
+            disabled_indicators: vec![TxPerHour::NAME.to_string()],
+            ..Default::default()
+        };
+        let registry = IndicatorRegistry::from_policy(&policy);
+
+        assert!(!registry.is_enabled(TxPerHour::NAME));
+        assert!(registry.is_enabled(WalletBalance::NAME));
+        assert_eq!(
+            registry.names().len(),
+            IndicatorRegistry::default().names().len() - 1
+        );
+    }
+
+    #[test]
+    fn test_registry_register_replaces_existing() {
+        let registry = IndicatorRegistry::empty()
+            .register::<WalletBalance>()
+            .register::<WalletBalance>();
+        assert_eq!(registry.names(), vec![WalletBalance::NAME]);
+    }
+
+    #[test]
+    fn test_registry_default_leaves_out_wallet_rewards() {
+        assert!(!IndicatorRegistry::default().is_enabled(WalletRewards::NAME));
+        assert!(!IndicatorRegistry::from_policy(&ScoringPolicy::default())
+            .is_enabled(WalletRewards::NAME));
+    }
+
+    #[test]
+    fn test_registry_policy_enables_optional_indicator() {
+        let policy = ScoringPolicy {
+            enabled_indicators: vec![WalletRewards::NAME.to_string(), "unknown".to_string()],
+            ..Default::default()
+        };
+        let registry = IndicatorRegistry::from_policy(&policy);
+
+        assert!(registry.is_enabled(WalletRewards::NAME));
+        assert_eq!(
+            registry.names().len(),
+            IndicatorRegistry::default().names().len() + 1
+        );
+    }
+}
@@ -1,18 +1,51 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
+use serde::Serialize;
 use solana_client::rpc_response::{
     RpcConfirmedTransactionStatusWithSignature, RpcPrioritizationFee,
 };
 use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
 
+use crate::wallet::token_holding::TokenHolding;
+
+use super::{
+    funding_source::account_keys,
+    indicator_registry::{Indicator, IndicatorContext},
+    reputation::ReputationPenalty,
+    scoring_policy::ScoringPolicy,
+};
+
+/// Rewards credited to the wallet in its transactions, in lamports. Transaction metadata only
+/// lists rewards paid to the slot leader, so staking rewards never show up here and the
+/// indicator only runs when enabled by the scoring policy
+#[derive(Debug, Serialize)]
 pub struct WalletRewards(pub i64);
 
+impl Indicator for WalletRewards {
+    const NAME: &'static str = "wallet_rewards";
+
+    fn from_context(context: &IndicatorContext) -> Self {
+        Self::calculate(&context.wallet.wallet_addr, context.confirmed_transactions)
+    }
+
+    fn penalty(&self, policy: &ScoringPolicy) -> ReputationPenalty {
+        (self, policy).into()
+    }
+}
+
 impl WalletRewards {
-    pub fn calculate(confirmed_transactions: &[EncodedConfirmedTransactionWithStatusMeta]) -> Self {
+    pub fn calculate(
+        wallet_addr: &str,
+        confirmed_transactions: &[EncodedConfirmedTransactionWithStatusMeta],
+    ) -> Self {
         let total_rewards = confirmed_transactions
             .iter()
             .filter_map(|tx| tx.transaction.meta.as_ref())
             .flat_map(|meta| meta.rewards.clone().unwrap_or_else(Vec::new))
+            .filter(|reward| reward.pubkey == wallet_addr)
             .map(|reward| reward.lamports)
             .sum();
 
@@ -20,12 +53,23 @@ impl WalletRewards {
     }
 }
 
+#[derive(Debug, Serialize)]
 pub struct TransactionsWithNewWallets(pub f64);
 
+impl Indicator for TransactionsWithNewWallets {
+    const NAME: &'static str = "transactions_with_new_wallets";
+
+    fn from_context(context: &IndicatorContext) -> Self {
+        Self::calculate(context.confirmed_transactions)
+    }
+
+    fn penalty(&self, policy: &ScoringPolicy) -> ReputationPenalty {
+        (self, policy).into()
+    }
+}
+
 impl TransactionsWithNewWallets {
-    pub fn calculate(
-        confirmed_transactions: Vec<EncodedConfirmedTransactionWithStatusMeta>,
-    ) -> Self {
+    pub fn calculate(confirmed_transactions: &[EncodedConfirmedTransactionWithStatusMeta]) -> Self {
         let total = confirmed_transactions.len();
 
         // Count transactions that include at least one account that appears to be newly funded.
@@ -56,21 +100,39 @@ impl TransactionsWithNewWallets {
     }
 }
 
+#[derive(Debug, Serialize)]
 pub struct WalletBalanceVolatility(pub f64);
 
+impl Indicator for WalletBalanceVolatility {
+    const NAME: &'static str = "wallet_balance_volatility";
+
+    fn from_context(context: &IndicatorContext) -> Self {
+        Self::calculate(&context.wallet.wallet_addr, context.confirmed_transactions)
+    }
+
+    fn penalty(&self, policy: &ScoringPolicy) -> ReputationPenalty {
+        (self, policy).into()
+    }
+}
+
 impl WalletBalanceVolatility {
+    /// Standard deviation of the wallet's post transaction balance, in Solana
     pub fn calculate(
-        confirmed_transactions: Vec<EncodedConfirmedTransactionWithStatusMeta>,
+        wallet_addr: &str,
+        confirmed_transactions: &[EncodedConfirmedTransactionWithStatusMeta],
     ) -> Self {
         let balances: Vec<f64> = confirmed_transactions
             .iter()
             .filter_map(|tx| {
-                // Check if metadata exists and contains at least one balance in post_balances.
+                // the wallet isn't necessarily the fee payer, so find its own balance
+                let index = account_keys(tx)?
+                    .iter()
+                    .position(|key| key == wallet_addr)?;
                 tx.transaction
                     .meta
                     .as_ref()
-                    .and_then(|meta| meta.post_balances.first())
-                    .map(|balance| *balance as f64)
+                    .and_then(|meta| meta.post_balances.get(index))
+                    .map(|balance| *balance as f64 / 1_000_000_000.0)
             })
             .collect();
 
@@ -101,12 +163,36 @@ impl WalletBalanceVolatility {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct WalletBalance(pub u64);
 
-#[derive(Debug)]
+impl Indicator for WalletBalance {
+    const NAME: &'static str = "wallet_balance";
+
+    fn from_context(context: &IndicatorContext) -> Self {
+        Self(context.wallet.account_balance)
+    }
+
+    fn penalty(&self, policy: &ScoringPolicy) -> ReputationPenalty {
+        (self, policy).into()
+    }
+}
+
+#[derive(Debug, Serialize)]
 pub struct TxPerHour(pub i64);
 
+impl Indicator for TxPerHour {
+    const NAME: &'static str = "tx_per_hour";
+
+    fn from_context(context: &IndicatorContext) -> Self {
+        Self::calculate(&context.wallet.transaction_history)
+    }
+
+    fn penalty(&self, policy: &ScoringPolicy) -> ReputationPenalty {
+        (self, policy).into()
+    }
+}
+
 impl TxPerHour {
     /// Calculates transaction volume over last 1000 transactions
     pub fn calculate(transaction_history: &[RpcConfirmedTransactionStatusWithSignature]) -> Self {
@@ -119,14 +205,31 @@ impl TxPerHour {
                     .and_then(|last_tx| last_tx.block_time),
             )
             .map(|(first_tx_time, last_tx_time)| (first_tx_time - last_tx_time).abs() / 3600)
-            .unwrap_or(0);
+            .unwrap_or(0)
+            // a history spanning less than an hour counts as an hour
+            .max(1);
         Self(transaction_history.len() as i64 / num_hours)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct DaysSinceLastBlock(pub u64);
 
+impl Indicator for DaysSinceLastBlock {
+    const NAME: &'static str = "days_since_last_block";
+
+    fn from_context(context: &IndicatorContext) -> Self {
+        Self::calculate(&context.wallet.transaction_history).unwrap_or_else(|| {
+            log::warn!("Days since last block calculation failed, using default max value");
+            Self(u64::MAX)
+        })
+    }
+
+    fn penalty(&self, policy: &ScoringPolicy) -> ReputationPenalty {
+        (self, policy).into()
+    }
+}
+
 impl DaysSinceLastBlock {
     pub fn calculate(
         transaction_history: &[RpcConfirmedTransactionStatusWithSignature],
@@ -146,6 +249,39 @@ impl DaysSinceLastBlock {
     }
 }
 
+#[derive(Debug, Serialize)]
+pub struct WalletAge {
+    /// Whole days since the wallet's first-ever transaction, unknown without one
+    pub days: Option<u64>,
+    /// Unix timestamp of the wallet's first-ever transaction
+    pub first_seen: Option<i64>,
+}
+
+impl Indicator for WalletAge {
+    const NAME: &'static str = "wallet_age";
+
+    fn from_context(context: &IndicatorContext) -> Self {
+        Self::calculate(context.wallet.first_seen)
+    }
+
+    fn penalty(&self, policy: &ScoringPolicy) -> ReputationPenalty {
+        (self, policy).into()
+    }
+}
+
+impl WalletAge {
+    pub fn calculate(first_seen: Option<i64>) -> Self {
+        let current_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards")
+            .as_secs();
+        let days = first_seen.map(|first_seen| {
+            current_time.saturating_sub(first_seen.max(0) as u64) / (60 * 60 * 24)
+        });
+        Self { days, first_seen }
+    }
+}
+
 #[derive(Debug)]
 pub struct PrioritizationFeesMetrics {
     pub avg_fee: f64,
@@ -178,9 +314,53 @@ impl PrioritizationFeesMetrics {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+pub struct AvgPrioritizationFee(pub f64);
+
+impl Indicator for AvgPrioritizationFee {
+    const NAME: &'static str = "avg_prio_fee";
+
+    fn from_context(context: &IndicatorContext) -> Self {
+        Self(PrioritizationFeesMetrics::calculate(&context.wallet.prioritization_fees).avg_fee)
+    }
+
+    fn penalty(&self, policy: &ScoringPolicy) -> ReputationPenalty {
+        (self, policy).into()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrioritizationFeeStdDeviation(pub f64);
+
+impl Indicator for PrioritizationFeeStdDeviation {
+    const NAME: &'static str = "prio_fee_std_devi";
+
+    fn from_context(context: &IndicatorContext) -> Self {
+        Self(
+            PrioritizationFeesMetrics::calculate(&context.wallet.prioritization_fees).std_deviation,
+        )
+    }
+
+    fn penalty(&self, policy: &ScoringPolicy) -> ReputationPenalty {
+        (self, policy).into()
+    }
+}
+
+#[derive(Debug, Serialize)]
 pub struct TransactionFailureRate(pub f64);
 
+impl Indicator for TransactionFailureRate {
+    const NAME: &'static str = "transaction_failure_rate";
+
+    fn from_context(context: &IndicatorContext) -> Self {
+        Self::calculate(&context.wallet.transaction_history)
+    }
+
+    fn penalty(&self, policy: &ScoringPolicy) -> ReputationPenalty {
+        (self, policy).into()
+    }
+}
+
 impl TransactionFailureRate {
     pub fn calculate(transaction_history: &[RpcConfirmedTransactionStatusWithSignature]) -> Self {
         if transaction_history.is_empty() {
@@ -203,9 +383,72 @@ impl TransactionFailureRate {
     }
 }
 
+#[derive(Debug, Serialize)]
+pub struct TokenPortfolio {
+    /// Number of distinct mints held above the dust amount
+    pub holdings: usize,
+    /// Percentage of the combined token amount held in the largest position, 0 without holdings.
+    /// Amounts of different mints aren't priced, so this measures how lopsided the positions are
+    pub concentration: f64,
+    /// Percentage of token accounts which are empty or only hold dust
+    pub dust_share: f64,
+}
+
+impl Indicator for TokenPortfolio {
+    const NAME: &'static str = "token_portfolio";
+
+    fn from_context(context: &IndicatorContext) -> Self {
+        Self::calculate(
+            &context.wallet.token_holdings,
+            context.policy.token_portfolio.dust_amount,
+        )
+    }
+
+    fn penalty(&self, policy: &ScoringPolicy) -> ReputationPenalty {
+        (self, policy).into()
+    }
+}
+
+impl TokenPortfolio {
+    pub fn calculate(token_holdings: &[TokenHolding], dust_amount: f64) -> Self {
+        if token_holdings.is_empty() {
+            return Self {
+                holdings: 0,
+                concentration: 0.0,
+                dust_share: 0.0,
+            };
+        }
+
+        let (dust, held): (Vec<_>, Vec<_>) = token_holdings
+            .iter()
+            .partition(|holding| holding.ui_amount() <= dust_amount);
+        let mut amounts_by_mint: HashMap<&str, f64> = HashMap::new();
+        for holding in held {
+            *amounts_by_mint.entry(holding.mint.as_str()).or_default() += holding.ui_amount();
+        }
+
+        let total_amount: f64 = amounts_by_mint.values().sum();
+        let largest_amount = amounts_by_mint.values().copied().fold(0.0, f64::max);
+        let concentration = if total_amount > 0.0 {
+            largest_amount / total_amount * 100.0
+        } else {
+            0.0
+        };
+
+        Self {
+            holdings: amounts_by_mint.len(),
+            concentration,
+            dust_share: dust.len() as f64 / token_holdings.len() as f64 * 100.0,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::reputation::reputation::{PenaltySeverity, ReputationPenalty};
+    use crate::reputation::{
+        reputation::{PenaltySeverity, ReputationPenalty},
+        scoring_policy::ScoringPolicy,
+    };
 
     use super::*;
 
@@ -235,6 +478,13 @@ mod tests {
             .collect();
         let tx_per_hour = TxPerHour::calculate(&transactions);
         assert_eq!(tx_per_hour.0, 10);
+
+        // histories spanning less than an hour, or nothing at all, don't divide by zero
+        let transactions: Vec<RpcConfirmedTransactionStatusWithSignature> = (0..3)
+            .map(|i| create_mock_transaction(Some(1000 + i * 100), false))
+            .collect();
+        assert_eq!(TxPerHour::calculate(&transactions).0, 3);
+        assert_eq!(TxPerHour::calculate(&[]).0, 0);
     }
 
     #[test]
@@ -254,7 +504,7 @@ mod tests {
         for (block_time, expected_severity) in test_cases {
             let transactions = vec![create_mock_transaction(Some(block_time as i64), false)];
             let days = DaysSinceLastBlock::calculate(&transactions).unwrap();
-            let penalty: ReputationPenalty = (&days).into();
+            let penalty: ReputationPenalty = (&days, &ScoringPolicy::default()).into();
             assert_eq!(
                 std::mem::discriminant(&penalty.severity),
                 std::mem::discriminant(&expected_severity),
@@ -315,4 +565,75 @@ mod tests {
         let expected_std_dev = (10.0f64).sqrt();
         assert!((metrics.std_deviation - expected_std_dev).abs() < 2.0);
     }
+
+    #[test]
+    fn test_wallet_age() {
+        let current_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let test_cases = vec![
+            (None, None, PenaltySeverity::None),
+            (
+                Some(current_time - 3 * 86400),
+                Some(3),
+                PenaltySeverity::High,
+            ),
+            (
+                Some(current_time - 15 * 86400),
+                Some(15),
+                PenaltySeverity::Medium,
+            ),
+            (
+                Some(current_time - 60 * 86400),
+                Some(60),
+                PenaltySeverity::Low,
+            ),
+            (
+                Some(current_time - 400 * 86400),
+                Some(400),
+                PenaltySeverity::None,
+            ),
+        ];
+
+        for (first_seen, expected_days, expected_severity) in test_cases {
+            let age = WalletAge::calculate(first_seen);
+            assert_eq!(age.days, expected_days);
+            let penalty: ReputationPenalty = (&age, &ScoringPolicy::default()).into();
+            assert_eq!(penalty.severity, expected_severity);
+        }
+
+        // an unknown age isn't reported as a young wallet
+        let penalty: ReputationPenalty =
+            (&WalletAge::calculate(None), &ScoringPolicy::default()).into();
+        assert_eq!(penalty.reasoning, vec!["Wallet age unknown".to_string()]);
+    }
+
+    fn create_mock_holding(mint: &str, amount: u64) -> TokenHolding {
+        TokenHolding {
+            token_account: String::new(),
+            mint: mint.to_string(),
+            amount,
+            decimals: 6,
+        }
+    }
+
+    #[test]
+    fn test_token_portfolio() {
+        let empty = TokenPortfolio::calculate(&[], 0.001);
+        assert_eq!(empty.holdings, 0);
+        assert_eq!(empty.dust_share, 0.0);
+
+        let holdings = vec![
+            create_mock_holding("usdc", 3_000_000),
+            create_mock_holding("bonk", 1_000_000),
+            create_mock_holding("jup", 0),
+            create_mock_holding("wif", 500),
+        ];
+        let portfolio = TokenPortfolio::calculate(&holdings, 0.001);
+        assert_eq!(portfolio.holdings, 2);
+        assert_eq!(portfolio.concentration, 75.0);
+        assert_eq!(portfolio.dust_share, 50.0);
+    }
 }
@@ -1,3 +1,8 @@
 pub mod associates;
+pub mod counterparties;
+pub mod funding_source;
+pub mod indicator_registry;
 pub mod indicators;
+pub mod report_diff;
 pub mod reputation;
+pub mod scoring_policy;
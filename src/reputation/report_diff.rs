@@ -0,0 +1,190 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::database::models::{RatingClassification, WalletMetrics, WalletReport};
+
+use super::reputation::{PenaltySeverity, ReputationPenalty};
+
+#[derive(Serialize, Debug)]
+pub struct PenaltyChange {
+    pub indicator: String,
+    pub from_severity: Option<PenaltySeverity>,
+    pub to_severity: Option<PenaltySeverity>,
+    pub reasoning: Vec<String>,
+}
+
+/// Structured difference between two reports of the same wallet
+#[derive(Serialize, Debug)]
+pub struct WalletReportDiff {
+    pub wallet_addr: String,
+    pub from_report_id: Uuid,
+    pub to_report_id: Uuid,
+    pub score_delta: i32,
+    pub from_classification: RatingClassification,
+    pub to_classification: RatingClassification,
+    pub classification_changed: bool,
+    /// Indicators which had no penalty in the older report but do in the newer one
+    pub penalties_appeared: Vec<PenaltyChange>,
+    /// Indicators which had a penalty in the older report but not in the newer one
+    pub penalties_disappeared: Vec<PenaltyChange>,
+    /// Indicators penalized in both reports with a different severity
+    pub penalties_changed: Vec<PenaltyChange>,
+}
+
+/// Penalties which actually affect the score, keyed by indicator name
+fn active_penalties(metrics: &WalletMetrics) -> BTreeMap<String, ReputationPenalty> {
+    serde_json::from_value::<BTreeMap<String, ReputationPenalty>>(metrics.penalties.clone())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(_, penalty)| penalty.severity != PenaltySeverity::None)
+        .collect()
+}
+
+impl WalletReportDiff {
+    pub fn new(
+        from: (&WalletReport, &WalletMetrics),
+        to: (&WalletReport, &WalletMetrics),
+    ) -> Result<Self> {
+        let ((from_report, from_metrics), (to_report, to_metrics)) = (from, to);
+        if from_report.wallet_addr != to_report.wallet_addr {
+            bail!(
+                "Reports {} and {} belong to different wallets",
+                from_report.id,
+                to_report.id
+            );
+        }
+
+        let from_penalties = active_penalties(from_metrics);
+        let to_penalties = active_penalties(to_metrics);
+        let indicators: BTreeSet<&String> =
+            from_penalties.keys().chain(to_penalties.keys()).collect();
+
+        let mut penalties_appeared = Vec::new();
+        let mut penalties_disappeared = Vec::new();
+        let mut penalties_changed = Vec::new();
+        for indicator in indicators {
+            let from_penalty = from_penalties.get(indicator);
+            let to_penalty = to_penalties.get(indicator);
+            let change = PenaltyChange {
+                indicator: indicator.clone(),
+                from_severity: from_penalty.map(|penalty| penalty.severity),
+                to_severity: to_penalty.map(|penalty| penalty.severity),
+                reasoning: to_penalty
+                    .or(from_penalty)
+                    .map(|penalty| penalty.reasoning.clone())
+                    .unwrap_or_default(),
+            };
+            match (from_penalty, to_penalty) {
+                (None, Some(_)) => penalties_appeared.push(change),
+                (Some(_), None) => penalties_disappeared.push(change),
+                (Some(from), Some(to)) if from.severity != to.severity => {
+                    penalties_changed.push(change)
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            wallet_addr: to_report.wallet_addr.clone(),
+            from_report_id: from_report.id,
+            to_report_id: to_report.id,
+            score_delta: to_report.rating_score - from_report.rating_score,
+            from_classification: from_report.rating_classification.clone(),
+            to_classification: to_report.rating_classification.clone(),
+            classification_changed: from_report.rating_classification
+                != to_report.rating_classification,
+            penalties_appeared,
+            penalties_disappeared,
+            penalties_changed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use serde_json::json;
+
+    use super::*;
+
+    fn create_mock_report(
+        wallet_addr: &str,
+        rating_score: i32,
+        penalties: serde_json::Value,
+    ) -> (WalletReport, WalletMetrics) {
+        let id = Uuid::new_v4();
+        let report = WalletReport {
+            id,
+            rating_classification: rating_score.into(),
+            rating_score,
+            case_report: serde_json::Value::Null,
+            report_creation_date: Utc::now().naive_local(),
+            wallet_addr: wallet_addr.to_string(),
+            scoring_policy_version: "default".to_string(),
+            user_id: None,
+        };
+        let metrics = WalletMetrics {
+            wallet_report_id: id,
+            transaction_failure_rate: 0.0,
+            avg_prio_fee: 0.0,
+            prio_fee_std_devi: 0.0,
+            days_since_last_block: 0,
+            tx_per_hour: 0,
+            wallet_balance: 0,
+            wallet_rewards: None,
+            wallet_balance_volatility: 0.0,
+            transactions_with_new_wallets: 0.0,
+            token_holdings: 0,
+            token_concentration: 0.0,
+            dust_token_account_share: 0.0,
+            wallet_age_days: None,
+            first_seen_at: None,
+            indicator_metrics: json!({}),
+            score_contributions: json!({}),
+            penalties,
+        };
+        (report, metrics)
+    }
+
+    #[test]
+    fn test_wallet_report_diff() {
+        let from = create_mock_report(
+            "wallet",
+            850,
+            json!({
+                "tx_per_hour": { "severity": "High", "reasoning": [] },
+                "wallet_balance": { "severity": "Low", "reasoning": [] },
+                "days_since_last_block": { "severity": "None", "reasoning": [] },
+            }),
+        );
+        let to = create_mock_report(
+            "wallet",
+            650,
+            json!({
+                "tx_per_hour": { "severity": "None", "reasoning": [] },
+                "wallet_balance": { "severity": "Medium", "reasoning": [] },
+                "days_since_last_block": { "severity": "High", "reasoning": [] },
+            }),
+        );
+
+        let diff = WalletReportDiff::new((&from.0, &from.1), (&to.0, &to.1)).unwrap();
+        assert_eq!(diff.score_delta, -200);
+        assert!(diff.classification_changed);
+        assert_eq!(
+            diff.penalties_appeared[0].indicator,
+            "days_since_last_block"
+        );
+        assert_eq!(diff.penalties_disappeared[0].indicator, "tx_per_hour");
+        assert_eq!(diff.penalties_changed[0].indicator, "wallet_balance");
+    }
+
+    #[test]
+    fn test_wallet_report_diff_different_wallets() {
+        let from = create_mock_report("wallet_a", 850, json!({}));
+        let to = create_mock_report("wallet_b", 850, json!({}));
+        assert!(WalletReportDiff::new((&from.0, &from.1), (&to.0, &to.1)).is_err());
+    }
+}
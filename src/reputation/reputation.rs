@@ -1,142 +1,261 @@
-use serde::Serialize;
+use std::collections::BTreeMap;
+
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
     database::models::{RatingClassification, WalletMetrics},
     reputation::indicators::{
-        DaysSinceLastBlock, PrioritizationFeesMetrics, TransactionFailureRate, TxPerHour,
-        WalletBalance,
+        AvgPrioritizationFee, DaysSinceLastBlock, PrioritizationFeeStdDeviation,
+        TransactionFailureRate, TxPerHour, WalletBalance,
+    },
+};
+
+use super::{
+    associates::{KnownCreditedAssociates, KnownDiscreditedAssociates},
+    funding_source::FundingSource,
+    indicator_registry::{Indicator, IndicatorContext, IndicatorRegistry, IndicatorResult},
+    indicators::{
+        TokenPortfolio, TransactionsWithNewWallets, WalletAge, WalletBalanceVolatility,
+        WalletRewards,
     },
-    wallet::wallet::Wallet,
+    scoring_policy::{ScoringPolicy, MAX_RATING_SCORE, MIN_RATING_SCORE},
 };
 
-use super::indicators::{TransactionsWithNewWallets, WalletBalanceVolatility, WalletRewards};
+const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
 
 #[derive(Serialize, Clone)]
 pub struct Reputation {
     pub id: Uuid,
-    pub penalties: Vec<ReputationPenalty>,
+    pub indicators: Vec<IndicatorResult>,
     pub rating_score: i32,
     pub rating_classification: RatingClassification,
     pub wallet_metrics: WalletMetrics,
+    pub funding_source: FundingSource,
+    pub scoring_policy_version: String,
 }
 
 impl Reputation {
-    fn calc_rating_score(penalties: &[ReputationPenalty]) -> i32 {
-        penalties.iter().fold(1000, |score, penalty| {
-            score
-                - match penalty.severity {
-                    PenaltySeverity::High => 250,
-                    PenaltySeverity::Medium => 150,
-                    PenaltySeverity::Low => 50,
-                    PenaltySeverity::None => 0,
-                }
-        })
+    fn calc_rating_score(indicators: &[IndicatorResult]) -> i32 {
+        indicators
+            .iter()
+            .fold(MAX_RATING_SCORE, |score, indicator| {
+                score + indicator.contribution
+            })
+            .clamp(MIN_RATING_SCORE, MAX_RATING_SCORE)
     }
 
-    pub fn new_from_wallet(wallet: &Wallet, id: Uuid) -> Self {
-        log::info!(
-            "Initializing reputation creation for wallet with report id: {}",
-            id
-        );
-
-        // calculate metrics/indicators
-        let tx_per_hour = TxPerHour::calculate(&wallet.transaction_history);
-        log::debug!("Computed transactions per hour: {:?}", tx_per_hour);
-
-        let wallet_balance = WalletBalance(wallet.account_balance);
-        log::debug!("Computed wallet balance: {:?}", wallet_balance);
-
-        let days_since_last_block = DaysSinceLastBlock::calculate(&wallet.transaction_history)
-            .unwrap_or_else(|| {
-                log::warn!("Days since last block calculation failed, using default max value");
-                DaysSinceLastBlock(u64::MAX)
-            });
-        log::debug!(
-            "Computed days since last block: {:?}",
-            days_since_last_block
-        );
+    fn calc_wallet_metrics(id: Uuid, indicators: &[IndicatorResult]) -> WalletMetrics {
+        let metrics: BTreeMap<&str, &serde_json::Value> = indicators
+            .iter()
+            .map(|indicator| (indicator.name.as_str(), &indicator.metric))
+            .collect();
+        let metric_f64 = |name: &str| {
+            metrics
+                .get(name)
+                .and_then(|metric| metric.as_f64())
+                .unwrap_or_default()
+        };
+        // u64 metrics such as the balance are stored in signed columns
+        let metric_i64 = |name: &str| {
+            metrics
+                .get(name)
+                .and_then(|metric| metric.as_i64().or(metric.as_u64().map(|m| m as i64)))
+                .unwrap_or_default()
+        };
+        // indicators made of several metrics serialize to an object
+        let metric_field = |name: &str, field: &str| {
+            metrics
+                .get(name)
+                .and_then(|metric| metric.get(field))
+                .and_then(|metric| metric.as_f64())
+                .unwrap_or_default()
+        };
 
-        let transaction_failure_rate =
-            TransactionFailureRate::calculate(&wallet.transaction_history);
-        log::debug!(
-            "Computed transaction failure rate: {:?}",
-            transaction_failure_rate
-        );
+        WalletMetrics {
+            wallet_report_id: id,
+            transaction_failure_rate: metric_f64(TransactionFailureRate::NAME),
+            avg_prio_fee: metric_f64(AvgPrioritizationFee::NAME),
+            prio_fee_std_devi: metric_f64(PrioritizationFeeStdDeviation::NAME),
+            days_since_last_block: metric_i64(DaysSinceLastBlock::NAME),
+            tx_per_hour: metric_i64(TxPerHour::NAME),
+            wallet_balance: metric_i64(WalletBalance::NAME),
+            // only set when the policy enabled the indicator
+            wallet_rewards: metrics
+                .get(WalletRewards::NAME)
+                .and_then(|metric| metric.as_i64()),
+            wallet_balance_volatility: metric_f64(WalletBalanceVolatility::NAME),
+            transactions_with_new_wallets: metric_f64(TransactionsWithNewWallets::NAME),
+            token_holdings: metric_field(TokenPortfolio::NAME, "holdings") as i64,
+            token_concentration: metric_field(TokenPortfolio::NAME, "concentration"),
+            dust_token_account_share: metric_field(TokenPortfolio::NAME, "dust_share"),
+            wallet_age_days: metrics
+                .get(WalletAge::NAME)
+                .and_then(|metric| metric.get("days"))
+                .and_then(|days| days.as_i64()),
+            first_seen_at: metrics
+                .get(WalletAge::NAME)
+                .and_then(|metric| metric.get("first_seen"))
+                .and_then(|first_seen| first_seen.as_i64())
+                .and_then(|first_seen| DateTime::from_timestamp(first_seen, 0))
+                .map(|first_seen| first_seen.naive_utc()),
+            indicator_metrics: serde_json::to_value(&metrics).unwrap_or_default(),
+            score_contributions: serde_json::to_value(
+                indicators
+                    .iter()
+                    .map(|indicator| (indicator.name.as_str(), indicator.contribution))
+                    .collect::<BTreeMap<&str, i32>>(),
+            )
+            .unwrap_or_default(),
+            penalties: serde_json::to_value(
+                indicators
+                    .iter()
+                    .map(|indicator| (indicator.name.as_str(), &indicator.penalty))
+                    .collect::<BTreeMap<&str, &ReputationPenalty>>(),
+            )
+            .unwrap_or_default(),
+        }
+    }
 
-        let prio_fee_metrics = PrioritizationFeesMetrics::calculate(&wallet.prioritization_fees);
-        log::debug!(
-            "Computed prioritization fees metrics: {:?}",
-            prio_fee_metrics
+    pub fn new_from_wallet(
+        context: &IndicatorContext,
+        registry: &IndicatorRegistry,
+        id: Uuid,
+    ) -> Self {
+        log::info!(
+            "Initializing reputation creation for wallet with report id: {} using scoring policy version: {}",
+            id,
+            context.policy.version
         );
 
-        let (fee_penalty_1, fee_penalty_2) = (&prio_fee_metrics).into();
-        let penalties = vec![
-            (&tx_per_hour).into(),
-            (&wallet_balance).into(),
-            (&days_since_last_block).into(),
-            (&transaction_failure_rate).into(),
-            fee_penalty_1,
-            fee_penalty_2,
-        ];
+        // calculate metrics/indicators
+        let indicators = registry.evaluate(context);
 
-        log::info!("Penalties calculated: {:?}", penalties);
-        let rating_score = Self::calc_rating_score(&penalties);
+        log::info!("Indicators calculated: {:?}", indicators);
+        let rating_score = Self::calc_rating_score(&indicators);
         log::info!("Calculated rating score: {:?}", rating_score);
 
         Self {
             id,
-            penalties,
+            wallet_metrics: Self::calc_wallet_metrics(id, &indicators),
+            indicators,
             rating_score,
             rating_classification: rating_score.into(),
-            wallet_metrics: WalletMetrics {
-                wallet_report_id: id,
-                transaction_failure_rate: transaction_failure_rate.0,
-                avg_prio_fee: prio_fee_metrics.avg_fee,
-                prio_fee_std_devi: prio_fee_metrics.std_deviation,
-                days_since_last_block: days_since_last_block.0 as i64,
-                tx_per_hour: tx_per_hour.0,
-                wallet_balance: wallet_balance.0 as i64,
-            },
+            funding_source: context.funding_source.clone(),
+            scoring_policy_version: context.policy.version.clone(),
         }
     }
 }
 
-#[derive(Serialize, Clone, Copy, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 pub enum PenaltySeverity {
     High,
     Medium,
     Low,
     None,
+    Bonus,
 }
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ReputationPenalty {
     pub severity: PenaltySeverity,
     pub reasoning: Vec<String>,
 }
 
-impl From<&WalletRewards> for ReputationPenalty {
-    fn from(wallet_rewards: &WalletRewards) -> Self {
-        let (severity, mut reasoning) = match wallet_rewards.0 {
-            b if b < 1 => (
-                PenaltySeverity::High,
-                vec!["Wallet rewards < 1".to_string()],
-            ),
-            b if b < 5 => (
-                PenaltySeverity::Medium,
-                vec!["Balance between 1 and 5".to_string()],
-            ),
-            b if b < 20 => (
-                PenaltySeverity::Low,
-                vec!["Balance between 5 and 20".to_string()],
-            ),
-            _ => (
-                PenaltySeverity::None,
-                vec!["Balance >= 20 Solana".to_string()],
-            ),
+impl PenaltySeverity {
+    /// Rank from least to most severe, a bonus being the least severe
+    fn rank(self) -> u8 {
+        match self {
+            PenaltySeverity::Bonus => 0,
+            PenaltySeverity::None => 1,
+            PenaltySeverity::Low => 2,
+            PenaltySeverity::Medium => 3,
+            PenaltySeverity::High => 4,
+        }
+    }
+
+    pub fn max(self, other: Self) -> Self {
+        if other.rank() > self.rank() {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+/// Mostly dust or a single lopsided position suggests a wallet used for airdrop farming or
+/// spam rather than holding a portfolio
+impl From<(&TokenPortfolio, &ScoringPolicy)> for ReputationPenalty {
+    fn from((portfolio, policy): (&TokenPortfolio, &ScoringPolicy)) -> Self {
+        let thresholds = &policy.token_portfolio;
+        let dust_severity = thresholds.dust_share.severity_above(portfolio.dust_share);
+        // a single holding says nothing about how diversified the wallet is
+        let concentration_severity = if portfolio.holdings > 1 {
+            thresholds
+                .concentration
+                .severity_above(portfolio.concentration)
+        } else {
+            PenaltySeverity::None
+        };
+
+        let mut reasoning = Vec::new();
+        if dust_severity != PenaltySeverity::None {
+            reasoning.push(format!(
+                "{:?} share of empty or dust token accounts",
+                dust_severity
+            ));
+        }
+        if concentration_severity != PenaltySeverity::None {
+            reasoning.push(format!(
+                "{:?} concentration in a single token",
+                concentration_severity
+            ));
+        }
+        if reasoning.is_empty() {
+            reasoning.push("No dust heavy or concentrated token portfolio".to_string());
+        }
+        reasoning.push(format!("Token holdings: {:?}", portfolio.holdings));
+        reasoning.push(format!(
+            "Largest position share: {:.2}%",
+            portfolio.concentration
+        ));
+        reasoning.push(format!(
+            "Dust token account share: {:.2}%",
+            portfolio.dust_share
+        ));
+        Self {
+            severity: dust_severity.max(concentration_severity),
+            reasoning,
+        }
+    }
+}
+
+/// Freshly created wallets are cheap to burn, so a short history earns less trust
+impl From<(&WalletAge, &ScoringPolicy)> for ReputationPenalty {
+    fn from((age, policy): (&WalletAge, &ScoringPolicy)) -> Self {
+        let thresholds = &policy.wallet_age;
+        // the walk back to the first transaction can fail, which says nothing about the wallet
+        let Some(days) = age.days else {
+            return Self {
+                severity: PenaltySeverity::None,
+                reasoning: vec!["Wallet age unknown".to_string()],
+            };
+        };
+        let severity = thresholds.severity_below(days);
+        let mut reasoning = match severity {
+            PenaltySeverity::High => vec![format!("Wallet younger than {} days", thresholds.high)],
+            PenaltySeverity::Medium => vec![format!(
+                "Wallet between {} and {} days old",
+                thresholds.high, thresholds.medium
+            )],
+            PenaltySeverity::Low => vec![format!(
+                "Wallet between {} and {} days old",
+                thresholds.medium, thresholds.low
+            )],
+            _ => vec![format!("Wallet at least {} days old", thresholds.low)],
         };
-        reasoning.push(format!("Wallet rewards: {:?}", wallet_rewards.0));
+        reasoning.push(format!("Wallet age in days: {:?}", days));
         Self {
             severity,
             reasoning,
@@ -144,27 +263,59 @@ impl From<&WalletRewards> for ReputationPenalty {
     }
 }
 
-impl From<&WalletBalance> for ReputationPenalty {
-    fn from(balance: &WalletBalance) -> Self {
-        let (severity, mut reasoning) = match balance.0 {
-            b if b < 001000000000 => (
-                PenaltySeverity::High,
-                vec!["Balance < 1 Solana".to_string()],
-            ),
-            b if b < 010000000000 => (
-                PenaltySeverity::Medium,
-                vec!["Balance between 1 and 10 Solana".to_string()],
-            ),
-            b if b < 100000000000 => (
-                PenaltySeverity::Low,
-                vec!["Balance between 10 and 100 Solana".to_string()],
-            ),
-            _ => (
-                PenaltySeverity::None,
-                vec!["Balance >= 100 Solana".to_string()],
-            ),
+impl From<(&WalletRewards, &ScoringPolicy)> for ReputationPenalty {
+    fn from((wallet_rewards, policy): (&WalletRewards, &ScoringPolicy)) -> Self {
+        let thresholds = &policy.wallet_rewards;
+        let rewards = wallet_rewards.0 as f64 / LAMPORTS_PER_SOL as f64;
+        let severity = thresholds.severity_below(rewards);
+        let mut reasoning = match severity {
+            PenaltySeverity::High => vec![format!("Wallet rewards < {} Solana", thresholds.high)],
+            PenaltySeverity::Medium => vec![format!(
+                "Wallet rewards between {} and {} Solana",
+                thresholds.high, thresholds.medium
+            )],
+            PenaltySeverity::Low => vec![format!(
+                "Wallet rewards between {} and {} Solana",
+                thresholds.medium, thresholds.low
+            )],
+            _ => vec![format!("Wallet rewards >= {} Solana", thresholds.low)],
         };
-        reasoning.push(format!("Solana balance: {:?}", balance.0 / 1_000_000_000));
+        reasoning.push(format!("Solana rewards: {:?}", rewards));
+        Self {
+            severity,
+            reasoning,
+        }
+    }
+}
+
+impl From<(&WalletBalance, &ScoringPolicy)> for ReputationPenalty {
+    fn from((balance, policy): (&WalletBalance, &ScoringPolicy)) -> Self {
+        let thresholds = &policy.wallet_balance;
+        let severity = thresholds.severity_below(balance.0);
+        let mut reasoning = match severity {
+            PenaltySeverity::High => vec![format!(
+                "Balance < {} Solana",
+                thresholds.high / LAMPORTS_PER_SOL
+            )],
+            PenaltySeverity::Medium => vec![format!(
+                "Balance between {} and {} Solana",
+                thresholds.high / LAMPORTS_PER_SOL,
+                thresholds.medium / LAMPORTS_PER_SOL
+            )],
+            PenaltySeverity::Low => vec![format!(
+                "Balance between {} and {} Solana",
+                thresholds.medium / LAMPORTS_PER_SOL,
+                thresholds.low / LAMPORTS_PER_SOL
+            )],
+            _ => vec![format!(
+                "Balance >= {} Solana",
+                thresholds.low / LAMPORTS_PER_SOL
+            )],
+        };
+        reasoning.push(format!(
+            "Solana balance: {:?}",
+            balance.0 / LAMPORTS_PER_SOL
+        ));
         Self {
             severity,
             reasoning,
@@ -175,18 +326,19 @@ impl From<&WalletBalance> for ReputationPenalty {
 /// Good transaction volume indicates high reputation
 /// Too high volume indicates automation and hence, lower reputation
 /// No volume indicates no reputation
-impl From<&TxPerHour> for ReputationPenalty {
-    fn from(tx_per_hour: &TxPerHour) -> Self {
+impl From<(&TxPerHour, &ScoringPolicy)> for ReputationPenalty {
+    fn from((tx_per_hour, policy): (&TxPerHour, &ScoringPolicy)) -> Self {
+        let thresholds = &policy.tx_per_hour;
         let (severity, mut reasoning) = match tx_per_hour.0 {
             0 => (
                 PenaltySeverity::High,
                 vec!["No transaction volume".to_string()],
             ),
-            v if v < 5 => (
+            v if v < thresholds.reasonable_from => (
                 PenaltySeverity::Low,
                 vec!["Low to Medium transaction volume".to_string()],
             ),
-            v if v < 25 => (
+            v if v < thresholds.too_high_from => (
                 PenaltySeverity::None,
                 vec!["Reasonable level of transaction volume".to_string()],
             ),
@@ -203,25 +355,14 @@ impl From<&TxPerHour> for ReputationPenalty {
     }
 }
 
-impl From<&DaysSinceLastBlock> for ReputationPenalty {
-    fn from(days: &DaysSinceLastBlock) -> Self {
-        let (severity, mut reasoning) = match days.0 {
-            0 => (
-                PenaltySeverity::None,
-                vec!["Recent activity in less than a day".to_string()],
-            ),
-            d if d < 7 => (
-                PenaltySeverity::Low,
-                vec!["Recent activity, less than a week ago".to_string()],
-            ),
-            d if d < 30 => (
-                PenaltySeverity::Medium,
-                vec!["Activity less than a month ago".to_string()],
-            ),
-            _ => (
-                PenaltySeverity::High,
-                vec!["No activity within a month".to_string()],
-            ),
+impl From<(&DaysSinceLastBlock, &ScoringPolicy)> for ReputationPenalty {
+    fn from((days, policy): (&DaysSinceLastBlock, &ScoringPolicy)) -> Self {
+        let severity = policy.days_since_last_block.severity_at_least(days.0);
+        let mut reasoning = match severity {
+            PenaltySeverity::None => vec!["Recent activity in less than a day".to_string()],
+            PenaltySeverity::Low => vec!["Recent activity, less than a week ago".to_string()],
+            PenaltySeverity::Medium => vec!["Activity less than a month ago".to_string()],
+            _ => vec!["No activity within a month".to_string()],
         };
         reasoning.push(format!("Days since last transactioon: {:?}", days.0));
         Self {
@@ -231,25 +372,16 @@ impl From<&DaysSinceLastBlock> for ReputationPenalty {
     }
 }
 
-impl From<&TransactionFailureRate> for ReputationPenalty {
-    fn from(failure_rate: &TransactionFailureRate) -> Self {
-        let (severity, mut reasoning) = match failure_rate.0 {
-            f if f > 10.0 => (
-                PenaltySeverity::High,
-                vec!["High transaction failure rate".to_string()],
-            ),
-            f if f > 5.0 => (
-                PenaltySeverity::Medium,
-                vec!["Moderate transaction failure rate".to_string()],
-            ),
-            f if f > 0.0 => (
-                PenaltySeverity::Low,
-                vec!["Low transaction failure rate".to_string()],
-            ),
-            _ => (
-                PenaltySeverity::None,
-                vec!["No transaction failures".to_string()],
-            ),
+impl From<(&TransactionFailureRate, &ScoringPolicy)> for ReputationPenalty {
+    fn from((failure_rate, policy): (&TransactionFailureRate, &ScoringPolicy)) -> Self {
+        let severity = policy
+            .transaction_failure_rate
+            .severity_above(failure_rate.0);
+        let mut reasoning = match severity {
+            PenaltySeverity::High => vec!["High transaction failure rate".to_string()],
+            PenaltySeverity::Medium => vec!["Moderate transaction failure rate".to_string()],
+            PenaltySeverity::Low => vec!["Low transaction failure rate".to_string()],
+            _ => vec!["No transaction failures".to_string()],
         };
         reasoning.push(format!("Transaction failure rate: {:?}", failure_rate.0));
         Self {
@@ -259,83 +391,81 @@ impl From<&TransactionFailureRate> for ReputationPenalty {
     }
 }
 
-impl From<&PrioritizationFeesMetrics> for (ReputationPenalty, ReputationPenalty) {
-    fn from(pfm: &PrioritizationFeesMetrics) -> Self {
-        let (avg_fee_severity, mut avg_fee_reasoning) = match pfm.avg_fee {
-            f if f > 10.0 => (
-                PenaltySeverity::None,
-                vec!["High average prioritization fee".to_string()],
-            ),
-            f if f > 5.0 => (
-                PenaltySeverity::None,
-                vec!["Medium average prioritization fee".to_string()],
-            ),
-            f if f > 0.0 => (
-                PenaltySeverity::None,
-                vec!["Low average prioritization fee".to_string()],
-            ),
-            _ => (
-                PenaltySeverity::High,
-                vec!["No prioritization fee used".to_string()],
-            ),
-        };
-        avg_fee_reasoning.push(format!("Average prioritization fee: {:?}", pfm.avg_fee));
+impl From<(&AvgPrioritizationFee, &ScoringPolicy)> for ReputationPenalty {
+    fn from((avg_fee, policy): (&AvgPrioritizationFee, &ScoringPolicy)) -> Self {
+        // a higher fee tier isn't penalized, only paying no fee is
+        let (severity, mut reasoning) =
+            match policy.prioritization_fee.avg_fee.severity_above(avg_fee.0) {
+                PenaltySeverity::High => (
+                    PenaltySeverity::None,
+                    vec!["High average prioritization fee".to_string()],
+                ),
+                PenaltySeverity::Medium => (
+                    PenaltySeverity::None,
+                    vec!["Medium average prioritization fee".to_string()],
+                ),
+                PenaltySeverity::Low => (
+                    PenaltySeverity::None,
+                    vec!["Low average prioritization fee".to_string()],
+                ),
+                _ => (
+                    PenaltySeverity::High,
+                    vec!["No prioritization fee used".to_string()],
+                ),
+            };
+        reasoning.push(format!("Average prioritization fee: {:?}", avg_fee.0));
+        Self {
+            severity,
+            reasoning,
+        }
+    }
+}
 
-        let (std_deviation_severity, mut std_deviation_reasoning) = match pfm.std_deviation {
-            f if f > 50.0 => (
-                PenaltySeverity::High,
-                vec!["Very high standard deviation in prioritization fee".to_string()],
-            ),
-            f if f > 25.0 => (
-                PenaltySeverity::Medium,
-                vec!["Medium standard deviation in prioritization fee".to_string()],
-            ),
-            f if f > 5.0 => (
-                PenaltySeverity::Low,
-                vec!["Low standard deviation in prioritization fee".to_string()],
-            ),
-            _ => (
-                PenaltySeverity::None,
-                vec!["Very low standard deviation in prioritization fee".to_string()],
-            ),
+impl From<(&PrioritizationFeeStdDeviation, &ScoringPolicy)> for ReputationPenalty {
+    fn from((std_deviation, policy): (&PrioritizationFeeStdDeviation, &ScoringPolicy)) -> Self {
+        let severity = policy
+            .prioritization_fee
+            .std_deviation
+            .severity_above(std_deviation.0);
+        let mut reasoning = match severity {
+            PenaltySeverity::High => {
+                vec!["Very high standard deviation in prioritization fee".to_string()]
+            }
+            PenaltySeverity::Medium => {
+                vec!["Medium standard deviation in prioritization fee".to_string()]
+            }
+            PenaltySeverity::Low => {
+                vec!["Low standard deviation in prioritization fee".to_string()]
+            }
+            _ => vec!["Very low standard deviation in prioritization fee".to_string()],
         };
-        std_deviation_reasoning.push(format!(
+        reasoning.push(format!(
             "Prioritization fee standard deviation: {:?}",
-            pfm.std_deviation
+            std_deviation.0
         ));
-
-        (
-            ReputationPenalty {
-                severity: avg_fee_severity,
-                reasoning: avg_fee_reasoning,
-            },
-            ReputationPenalty {
-                severity: std_deviation_severity,
-                reasoning: std_deviation_reasoning,
-            },
-        )
+        Self {
+            severity,
+            reasoning,
+        }
     }
 }
 
-impl From<&WalletBalanceVolatility> for ReputationPenalty {
-    fn from(balance_volatility: &WalletBalanceVolatility) -> Self {
-        let (severity, mut reasoning) = match balance_volatility.0 {
-            f if f > 25.0 => (
-                PenaltySeverity::High,
-                vec!["Very high standard deviation in balance volatility".to_string()],
-            ),
-            f if f > 10.0 => (
-                PenaltySeverity::Medium,
-                vec!["Medium standard deviation in balance volatility".to_string()],
-            ),
-            f if f > 5.0 => (
-                PenaltySeverity::Low,
-                vec!["Low standard deviation in balance volatility".to_string()],
-            ),
-            _ => (
-                PenaltySeverity::None,
-                vec!["Very low standard deviation in balance volatility".to_string()],
-            ),
+impl From<(&WalletBalanceVolatility, &ScoringPolicy)> for ReputationPenalty {
+    fn from((balance_volatility, policy): (&WalletBalanceVolatility, &ScoringPolicy)) -> Self {
+        let severity = policy
+            .wallet_balance_volatility
+            .severity_above(balance_volatility.0);
+        let mut reasoning = match severity {
+            PenaltySeverity::High => {
+                vec!["Very high standard deviation in balance volatility".to_string()]
+            }
+            PenaltySeverity::Medium => {
+                vec!["Medium standard deviation in balance volatility".to_string()]
+            }
+            PenaltySeverity::Low => {
+                vec!["Low standard deviation in balance volatility".to_string()]
+            }
+            _ => vec!["Very low standard deviation in balance volatility".to_string()],
         };
         reasoning.push(format!(
             "Balance volatility standard deviation: {:?}",
@@ -349,30 +479,124 @@ impl From<&WalletBalanceVolatility> for ReputationPenalty {
     }
 }
 
-impl From<&TransactionsWithNewWallets> for ReputationPenalty {
-    fn from(transactions_with_new_wallets: &TransactionsWithNewWallets) -> Self {
-        let (severity, mut reasoning) = match transactions_with_new_wallets.0 {
-            p if p > 30.0 => (
-                PenaltySeverity::High,
-                vec!["Very high % of transactions with new wallets".to_string()],
+impl From<(&TransactionsWithNewWallets, &ScoringPolicy)> for ReputationPenalty {
+    fn from(
+        (transactions_with_new_wallets, policy): (&TransactionsWithNewWallets, &ScoringPolicy),
+    ) -> Self {
+        let severity = policy
+            .transactions_with_new_wallets
+            .severity_above(transactions_with_new_wallets.0);
+        let mut reasoning = match severity {
+            PenaltySeverity::High => {
+                vec!["Very high % of transactions with new wallets".to_string()]
+            }
+            PenaltySeverity::Medium => {
+                vec!["Medium/high % of transactions with new wallets".to_string()]
+            }
+            PenaltySeverity::Low => vec!["Low % of transactions with new wallets".to_string()],
+            _ => vec!["Very low % of transactions with new wallets".to_string()],
+        };
+        reasoning.push(format!(
+            "% Transactions with new wallets: {:?}",
+            transactions_with_new_wallets.0
+        ));
+        Self {
+            severity,
+            reasoning,
+        }
+    }
+}
+
+/// Transacting with wallets that were previously rated as discredited lowers reputation
+impl From<(&KnownDiscreditedAssociates, &ScoringPolicy)> for ReputationPenalty {
+    fn from(
+        (discredited_associates, policy): (&KnownDiscreditedAssociates, &ScoringPolicy),
+    ) -> Self {
+        let severity = policy
+            .discredited_associates
+            .severity_at_least(discredited_associates.wallets.len());
+        let mut reasoning = match severity {
+            PenaltySeverity::High => vec!["Many known discredited associates".to_string()],
+            PenaltySeverity::Medium => vec!["Several known discredited associates".to_string()],
+            PenaltySeverity::Low => vec!["Few known discredited associates".to_string()],
+            _ => vec!["No known discredited associates".to_string()],
+        };
+        reasoning.extend(
+            discredited_associates
+                .wallets
+                .iter()
+                .map(|wallet| format!("Known discredited associate: {}", wallet.wallet_addr)),
+        );
+        Self {
+            severity,
+            reasoning,
+        }
+    }
+}
+
+/// Being funded, directly or a few hops back, by a wallet previously rated as discredited
+/// suggests a wallet created by the same actor
+impl From<(&FundingSource, &ScoringPolicy)> for ReputationPenalty {
+    fn from((funding_source, policy): (&FundingSource, &ScoringPolicy)) -> Self {
+        let (severity, mut reasoning) = match (
+            funding_source.discredited_funder_hops,
+            &funding_source.exchange,
+        ) {
+            (Some(hops), _) => {
+                let severity = policy
+                    .funding_source
+                    .discredited_funder_hops
+                    .severity_below(hops);
+                (
+                    severity,
+                    vec![format!(
+                        "Funded by a known discredited wallet {} hop(s) back",
+                        hops
+                    )],
+                )
+            }
+            (None, Some(exchange)) => (
+                PenaltySeverity::None,
+                vec![format!("Funded from exchange: {}", exchange)],
             ),
-            p if p > 20.0 => (
-                PenaltySeverity::High,
-                vec!["Medium/high % of transactions with new wallets".to_string()],
+            (None, None) if funding_source.path.is_empty() => (
+                PenaltySeverity::None,
+                vec!["No inbound funding transfer found".to_string()],
             ),
-            p if p > 10.0 => (
-                PenaltySeverity::High,
-                vec!["Low % of transactions with new wallets".to_string()],
+            (None, None) => (
+                PenaltySeverity::None,
+                vec!["No known discredited funders".to_string()],
+            ),
+        };
+        if let Some(path) = funding_source.describe_path() {
+            reasoning.push(format!("Funding path: {}", path));
+        }
+        Self {
+            severity,
+            reasoning,
+        }
+    }
+}
+
+/// Transacting with wallets that were previously rated as credited raises reputation
+impl From<(&KnownCreditedAssociates, &ScoringPolicy)> for ReputationPenalty {
+    fn from((credited_associates, policy): (&KnownCreditedAssociates, &ScoringPolicy)) -> Self {
+        let (severity, mut reasoning) = match credited_associates.wallets.len() {
+            n if n >= policy.min_credited_associates && n > 0 => (
+                PenaltySeverity::Bonus,
+                vec!["Known credited associates".to_string()],
             ),
             _ => (
                 PenaltySeverity::None,
-                vec!["Very low % of transactions with new wallets".to_string()],
+                vec!["No known credited associates".to_string()],
             ),
         };
-        reasoning.push(format!(
-            "% Transactions with new wallets: {:?}",
-            transactions_with_new_wallets.0
-        ));
+        reasoning.extend(
+            credited_associates
+                .wallets
+                .iter()
+                .map(|wallet| format!("Known credited associate: {}", wallet.wallet_addr)),
+        );
         Self {
             severity,
             reasoning,
@@ -382,6 +606,8 @@ impl From<&TransactionsWithNewWallets> for ReputationPenalty {
 
 #[cfg(test)]
 mod tests {
+    use crate::{database::models::KnownDiscreditedWallet, reputation::funding_source::FundingHop};
+
     use super::*;
 
     #[test]
@@ -394,7 +620,8 @@ mod tests {
         ];
 
         for (balance, expected_severity) in test_cases {
-            let penalty: ReputationPenalty = (&WalletBalance(balance)).into();
+            let penalty: ReputationPenalty =
+                (&WalletBalance(balance), &ScoringPolicy::default()).into();
             assert_eq!(
                 std::mem::discriminant(&penalty.severity),
                 std::mem::discriminant(&expected_severity),
@@ -415,7 +642,8 @@ mod tests {
         ];
 
         for (tx_per_hour, expected_severity) in test_cases {
-            let penalty: ReputationPenalty = (&TxPerHour(tx_per_hour)).into();
+            let penalty: ReputationPenalty =
+                (&TxPerHour(tx_per_hour), &ScoringPolicy::default()).into();
             assert_eq!(
                 std::mem::discriminant(&penalty.severity),
                 std::mem::discriminant(&expected_severity),
@@ -425,4 +653,193 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_transactions_with_new_wallets_penalties() {
+        let test_cases = vec![
+            (5.0, PenaltySeverity::None),
+            (15.0, PenaltySeverity::Low),
+            (25.0, PenaltySeverity::Medium),
+            (50.0, PenaltySeverity::High),
+        ];
+
+        for (percentage, expected_severity) in test_cases {
+            let penalty: ReputationPenalty = (
+                &TransactionsWithNewWallets(percentage),
+                &ScoringPolicy::default(),
+            )
+                .into();
+            assert_eq!(
+                std::mem::discriminant(&penalty.severity),
+                std::mem::discriminant(&expected_severity),
+                "{}% transactions with new wallets should have {:?} severity",
+                percentage,
+                expected_severity
+            );
+        }
+    }
+
+    #[test]
+    fn test_avg_prioritization_fee_penalties() {
+        let test_cases = vec![
+            (0.0, PenaltySeverity::High, "No prioritization fee used"),
+            (3.0, PenaltySeverity::None, "Low average prioritization fee"),
+            (
+                7.0,
+                PenaltySeverity::None,
+                "Medium average prioritization fee",
+            ),
+            (
+                20.0,
+                PenaltySeverity::None,
+                "High average prioritization fee",
+            ),
+        ];
+
+        for (avg_fee, expected_severity, expected_reasoning) in test_cases {
+            let penalty: ReputationPenalty =
+                (&AvgPrioritizationFee(avg_fee), &ScoringPolicy::default()).into();
+            assert_eq!(penalty.severity, expected_severity);
+            assert_eq!(penalty.reasoning[0], expected_reasoning);
+        }
+    }
+
+    #[test]
+    fn test_wallet_rewards_penalties() {
+        let test_cases = vec![
+            (0, PenaltySeverity::High),             // no rewards
+            (50_000_000, PenaltySeverity::Medium),  // 0.05 SOL
+            (500_000_000, PenaltySeverity::Low),    // 0.5 SOL
+            (2_000_000_000, PenaltySeverity::None), // 2 SOL
+        ];
+
+        for (rewards, expected_severity) in test_cases {
+            let penalty: ReputationPenalty =
+                (&WalletRewards(rewards), &ScoringPolicy::default()).into();
+            assert_eq!(
+                penalty.severity, expected_severity,
+                "Rewards of {} lamports should have {:?} severity",
+                rewards, expected_severity
+            );
+        }
+    }
+
+    #[test]
+    fn test_wallet_metrics_leave_out_indicators_which_did_not_run() {
+        let metrics = Reputation::calc_wallet_metrics(Uuid::nil(), &[]);
+        assert_eq!(metrics.wallet_rewards, None);
+        assert_eq!(metrics.wallet_age_days, None);
+
+        let rewards = IndicatorResult {
+            name: WalletRewards::NAME.to_string(),
+            metric: serde_json::json!(5_000),
+            penalty: ReputationPenalty {
+                severity: PenaltySeverity::High,
+                reasoning: vec![],
+            },
+            weight: 1.0,
+            contribution: -250,
+        };
+        let metrics = Reputation::calc_wallet_metrics(Uuid::nil(), &[rewards]);
+        assert_eq!(metrics.wallet_rewards, Some(5_000));
+    }
+
+    #[test]
+    fn test_rating_score_is_weighted_and_clamped() {
+        let policy = ScoringPolicy::default();
+        let indicator_result = |name: &str, severity: PenaltySeverity| IndicatorResult {
+            name: name.to_string(),
+            metric: serde_json::Value::Null,
+            penalty: ReputationPenalty {
+                severity,
+                reasoning: vec![],
+            },
+            weight: policy.weight(name),
+            contribution: policy.contribution(name, severity),
+        };
+
+        // no prioritization fee weighs less than a high failure rate
+        let no_prio_fee = indicator_result(AvgPrioritizationFee::NAME, PenaltySeverity::High);
+        let failure_rate = indicator_result(TransactionFailureRate::NAME, PenaltySeverity::High);
+        assert_eq!(no_prio_fee.contribution, -100);
+        assert_eq!(failure_rate.contribution, -250);
+        assert_eq!(
+            Reputation::calc_rating_score(&[no_prio_fee, failure_rate]),
+            650
+        );
+
+        let all_high: Vec<IndicatorResult> = (0..10)
+            .map(|_| indicator_result(TxPerHour::NAME, PenaltySeverity::High))
+            .collect();
+        assert_eq!(Reputation::calc_rating_score(&all_high), MIN_RATING_SCORE);
+
+        let all_bonus: Vec<IndicatorResult> = (0..3)
+            .map(|_| indicator_result(KnownCreditedAssociates::NAME, PenaltySeverity::Bonus))
+            .collect();
+        assert_eq!(Reputation::calc_rating_score(&all_bonus), MAX_RATING_SCORE);
+    }
+
+    #[test]
+    fn test_discredited_associates_penalties() {
+        let test_cases = vec![
+            (0, PenaltySeverity::None),
+            (1, PenaltySeverity::Low),
+            (4, PenaltySeverity::Medium),
+            (10, PenaltySeverity::High),
+        ];
+
+        for (num_associates, expected_severity) in test_cases {
+            let associates = KnownDiscreditedAssociates {
+                wallets: (0..num_associates)
+                    .map(|i| KnownDiscreditedWallet::new(format!("wallet_{}", i)))
+                    .collect(),
+            };
+            let penalty: ReputationPenalty = (&associates, &ScoringPolicy::default()).into();
+            assert_eq!(
+                std::mem::discriminant(&penalty.severity),
+                std::mem::discriminant(&expected_severity),
+                "{} discredited associates should have {:?} severity",
+                num_associates,
+                expected_severity
+            );
+            // one summary line plus one line per matched address
+            assert_eq!(penalty.reasoning.len(), num_associates + 1);
+        }
+    }
+
+    #[test]
+    fn test_funding_source_penalties() {
+        let funding_source = |hops: usize, discredited: bool| FundingSource {
+            path: (0..hops)
+                .map(|i| FundingHop {
+                    funder: format!("funder_{}", i + 1),
+                    funded: format!("funder_{}", i),
+                    signature: String::new(),
+                    lamports: 1_000_000_000,
+                    block_time: None,
+                })
+                .collect(),
+            discredited_funder_hops: discredited.then_some(hops),
+            exchange: None,
+        };
+        let test_cases = vec![
+            (funding_source(0, false), PenaltySeverity::None),
+            (funding_source(3, false), PenaltySeverity::None),
+            (funding_source(1, true), PenaltySeverity::High),
+            (funding_source(2, true), PenaltySeverity::Medium),
+            (funding_source(3, true), PenaltySeverity::Low),
+        ];
+
+        for (funding_source, expected_severity) in test_cases {
+            let penalty: ReputationPenalty = (&funding_source, &ScoringPolicy::default()).into();
+            assert_eq!(penalty.severity, expected_severity);
+        }
+
+        let penalty: ReputationPenalty =
+            (&funding_source(2, true), &ScoringPolicy::default()).into();
+        assert_eq!(
+            penalty.reasoning.last().unwrap(),
+            "Funding path: funder_0 <- funder_1 <- funder_2"
+        );
+    }
 }
@@ -0,0 +1,338 @@
+use std::{collections::BTreeMap, env, fs, path::Path};
+
+use anyhow::Result;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    indicator_registry::Indicator, indicators::AvgPrioritizationFee, reputation::PenaltySeverity,
+};
+
+const DEFAULT_POLICY_VERSION: &str = "default";
+const DEFAULT_INDICATOR_WEIGHT: f64 = 1.0;
+pub const MIN_RATING_SCORE: i32 = 0;
+pub const MAX_RATING_SCORE: i32 = 1000;
+
+/// Severity boundaries for a single indicator, from most to least severe
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Thresholds<T> {
+    pub high: T,
+    pub medium: T,
+    pub low: T,
+}
+
+impl<T: PartialOrd> Thresholds<T> {
+    /// Larger values are worse, a value must exceed a boundary to reach its severity
+    pub fn severity_above(&self, value: T) -> PenaltySeverity {
+        match value {
+            v if v > self.high => PenaltySeverity::High,
+            v if v > self.medium => PenaltySeverity::Medium,
+            v if v > self.low => PenaltySeverity::Low,
+            _ => PenaltySeverity::None,
+        }
+    }
+
+    /// Larger values are worse, a value equal to a boundary reaches its severity
+    pub fn severity_at_least(&self, value: T) -> PenaltySeverity {
+        match value {
+            v if v >= self.high => PenaltySeverity::High,
+            v if v >= self.medium => PenaltySeverity::Medium,
+            v if v >= self.low => PenaltySeverity::Low,
+            _ => PenaltySeverity::None,
+        }
+    }
+
+    /// Smaller values are worse, a value must be under a boundary to reach its severity
+    pub fn severity_below(&self, value: T) -> PenaltySeverity {
+        match value {
+            v if v < self.high => PenaltySeverity::High,
+            v if v < self.medium => PenaltySeverity::Medium,
+            v if v < self.low => PenaltySeverity::Low,
+            _ => PenaltySeverity::None,
+        }
+    }
+}
+
+/// Points deducted from the rating score per penalty severity
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SeverityDeductions {
+    pub high: i32,
+    pub medium: i32,
+    pub low: i32,
+    pub bonus: i32,
+}
+
+impl SeverityDeductions {
+    pub fn deduction(&self, severity: PenaltySeverity) -> i32 {
+        match severity {
+            PenaltySeverity::High => self.high,
+            PenaltySeverity::Medium => self.medium,
+            PenaltySeverity::Low => self.low,
+            PenaltySeverity::None => 0,
+            PenaltySeverity::Bonus => -self.bonus,
+        }
+    }
+}
+
+/// Rating scores at which a wallet is tracked as a known discredited or credited wallet
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RatingBoundaries {
+    pub discredited: i32,
+    pub credited: i32,
+}
+
+/// Transaction volume is penalized both when too low and when too high
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TxPerHourThresholds {
+    pub reasonable_from: i64,
+    pub too_high_from: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PrioritizationFeeThresholds {
+    /// Fee tiers reported for an average fee above each boundary, an average fee at or below
+    /// `low` counts as no prioritization fee and is penalized
+    pub avg_fee: Thresholds<f64>,
+    pub std_deviation: Thresholds<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TokenPortfolioThresholds {
+    /// Token accounts holding at most this many tokens count as dust
+    pub dust_amount: f64,
+    /// Percentage, checked with `severity_above`
+    pub dust_share: Thresholds<f64>,
+    /// Percentage, checked with `severity_above` once the wallet holds several tokens
+    pub concentration: Thresholds<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FundingSourceThresholds {
+    /// Number of funders followed back from the wallet
+    pub max_hops: usize,
+    /// Pages of 1000 signatures walked to reach an address's first transactions, tracing
+    /// stops at addresses with a longer history such as exchanges and other busy wallets
+    pub max_signature_pages: usize,
+    /// Hops to a known discredited funder, checked with `severity_below`
+    pub discredited_funder_hops: Thresholds<usize>,
+    /// Exchange labels keyed by wallet address, tracing stops at an exchange as its own
+    /// funding says nothing about its customers
+    #[serde(default)]
+    pub exchanges: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScoringPolicy {
+    pub version: String,
+    /// Names of registered indicators which should not be evaluated
+    #[serde(default)]
+    pub disabled_indicators: Vec<String>,
+    /// Names of optional indicators, left out of the default registry, which should be evaluated
+    #[serde(default)]
+    pub enabled_indicators: Vec<String>,
+    pub deductions: SeverityDeductions,
+    /// Multiplier applied to an indicator's deduction, indicators not listed have a weight of 1
+    #[serde(default)]
+    pub weights: BTreeMap<String, f64>,
+    pub rating_boundaries: RatingBoundaries,
+    /// Lamports, checked with `severity_below`
+    pub wallet_balance: Thresholds<u64>,
+    pub tx_per_hour: TxPerHourThresholds,
+    /// Days, checked with `severity_at_least`
+    pub days_since_last_block: Thresholds<u64>,
+    /// Percentage, checked with `severity_above`
+    pub transaction_failure_rate: Thresholds<f64>,
+    pub prioritization_fee: PrioritizationFeeThresholds,
+    /// Solana, checked with `severity_below`
+    pub wallet_rewards: Thresholds<f64>,
+    /// Solana, checked with `severity_above`
+    pub wallet_balance_volatility: Thresholds<f64>,
+    /// Percentage, checked with `severity_above`
+    pub transactions_with_new_wallets: Thresholds<f64>,
+    pub token_portfolio: TokenPortfolioThresholds,
+    /// Days since the first transaction, checked with `severity_below`
+    pub wallet_age: Thresholds<u64>,
+    pub funding_source: FundingSourceThresholds,
+    /// Number of associates, checked with `severity_at_least`
+    pub discredited_associates: Thresholds<usize>,
+    pub min_credited_associates: usize,
+}
+
+impl Default for ScoringPolicy {
+    fn default() -> Self {
+        Self {
+            version: DEFAULT_POLICY_VERSION.to_string(),
+            disabled_indicators: Vec::new(),
+            enabled_indicators: Vec::new(),
+            deductions: SeverityDeductions {
+                high: 250,
+                medium: 150,
+                low: 50,
+                bonus: 50,
+            },
+            // having no prioritization fee alone shouldn't cost as much as failing transactions
+            weights: BTreeMap::from([(AvgPrioritizationFee::NAME.to_string(), 0.4)]),
+            rating_boundaries: RatingBoundaries {
+                discredited: 400,
+                credited: 800,
+            },
+            wallet_balance: Thresholds {
+                high: 1_000_000_000,
+                medium: 10_000_000_000,
+                low: 100_000_000_000,
+            },
+            tx_per_hour: TxPerHourThresholds {
+                reasonable_from: 5,
+                too_high_from: 25,
+            },
+            days_since_last_block: Thresholds {
+                high: 30,
+                medium: 7,
+                low: 1,
+            },
+            transaction_failure_rate: Thresholds {
+                high: 10.0,
+                medium: 5.0,
+                low: 0.0,
+            },
+            prioritization_fee: PrioritizationFeeThresholds {
+                avg_fee: Thresholds {
+                    high: 10.0,
+                    medium: 5.0,
+                    low: 0.0,
+                },
+                std_deviation: Thresholds {
+                    high: 50.0,
+                    medium: 25.0,
+                    low: 5.0,
+                },
+            },
+            wallet_rewards: Thresholds {
+                high: 0.01,
+                medium: 0.1,
+                low: 1.0,
+            },
+            wallet_balance_volatility: Thresholds {
+                high: 25.0,
+                medium: 10.0,
+                low: 5.0,
+            },
+            transactions_with_new_wallets: Thresholds {
+                high: 30.0,
+                medium: 20.0,
+                low: 10.0,
+            },
+            token_portfolio: TokenPortfolioThresholds {
+                dust_amount: 0.001,
+                dust_share: Thresholds {
+                    high: 75.0,
+                    medium: 50.0,
+                    low: 25.0,
+                },
+                concentration: Thresholds {
+                    high: 99.0,
+                    medium: 95.0,
+                    low: 90.0,
+                },
+            },
+            wallet_age: Thresholds {
+                high: 7,
+                medium: 30,
+                low: 90,
+            },
+            funding_source: FundingSourceThresholds {
+                max_hops: 3,
+                max_signature_pages: 5,
+                discredited_funder_hops: Thresholds {
+                    high: 2,
+                    medium: 3,
+                    low: 4,
+                },
+                exchanges: BTreeMap::new(),
+            },
+            discredited_associates: Thresholds {
+                high: 6,
+                medium: 3,
+                low: 1,
+            },
+            min_credited_associates: 1,
+        }
+    }
+}
+
+impl ScoringPolicy {
+    pub fn weight(&self, indicator: &str) -> f64 {
+        self.weights
+            .get(indicator)
+            .copied()
+            .unwrap_or(DEFAULT_INDICATOR_WEIGHT)
+    }
+
+    /// Signed change to the rating score caused by an indicator's penalty
+    pub fn contribution(&self, indicator: &str, severity: PenaltySeverity) -> i32 {
+        -(self.weight(indicator) * self.deductions.deduction(severity) as f64).round() as i32
+    }
+
+    /// Loads the policy from the file at `SCORING_POLICY_PATH`, falling back to the default policy
+    pub fn load() -> Result<Self> {
+        match env::var("SCORING_POLICY_PATH") {
+            Ok(path) => Self::from_file(&path),
+            Err(_) => {
+                info!("SCORING_POLICY_PATH not set, using default scoring policy");
+                Ok(Self::default())
+            }
+        }
+    }
+
+    /// Parses a TOML or JSON policy file, chosen by the file extension
+    pub fn from_file(path: &str) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let policy: Self = match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&contents)?,
+            _ => serde_json::from_str(&contents)?,
+        };
+        info!(
+            "Loaded scoring policy version {} from {}",
+            policy.version, path
+        );
+        Ok(policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example_policy_matches_default() {
+        let mut example: ScoringPolicy =
+            toml::from_str(include_str!("../../scoring_policy.example.toml")).unwrap();
+        example.version = DEFAULT_POLICY_VERSION.to_string();
+        assert_eq!(
+            serde_json::to_value(&example).unwrap(),
+            serde_json::to_value(ScoringPolicy::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let thresholds = Thresholds {
+            high: 30,
+            medium: 7,
+            low: 1,
+        };
+        let test_cases = vec![
+            (0, PenaltySeverity::None),
+            (1, PenaltySeverity::Low),
+            (7, PenaltySeverity::Medium),
+            (30, PenaltySeverity::High),
+        ];
+
+        for (value, expected_severity) in test_cases {
+            assert_eq!(
+                std::mem::discriminant(&thresholds.severity_at_least(value)),
+                std::mem::discriminant(&expected_severity),
+            );
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::Result;
+use chrono::Utc;
+use log::{error, info};
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        models::{ReportJob, ReportJobState},
+        postgres::Database,
+    },
+    jobs::async_jobs::WalletReportJob,
+    pulsar::pulsar::{PulsarClient, PulsarProducer},
+    webhooks::webhooks::WebhookClient,
+    worker::worker::WALLET_REPUTATION_TOPIC,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Periodically enqueues report jobs for watchlisted wallets whose last report is stale, fails
+/// report jobs which stopped progressing and redrives webhook deliveries which never finished
+pub struct WatchlistScheduler {
+    database: Database,
+    job_producer: PulsarProducer,
+    webhook_client: WebhookClient,
+}
+
+impl WatchlistScheduler {
+    pub async fn new() -> Self {
+        info!("Initializing WatchlistScheduler...");
+        let pulsar_client = PulsarClient::new().await;
+        Self {
+            database: Database::connect().expect("Should be able to connect to db"),
+            job_producer: pulsar_client.create_producer(WALLET_REPUTATION_TOPIC).await,
+            webhook_client: WebhookClient::new(),
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<()> {
+        info!("Scheduler started polling watchlists.");
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.fail_stale_report_jobs() {
+                error!("Failed to expire stale report jobs: {:?}", e);
+            }
+            if let Err(e) = self.enqueue_stale_entries().await {
+                error!("Failed to schedule watchlist reports: {:?}", e);
+            }
+            if let Err(e) = self
+                .webhook_client
+                .redrive_unfinished_deliveries(&mut self.database)
+            {
+                error!("Failed to redrive webhook deliveries: {:?}", e);
+            }
+        }
+    }
+
+    /// Fails jobs which stopped progressing, so they no longer block new reports of the wallet
+    fn fail_stale_report_jobs(&mut self) -> Result<()> {
+        let now = Utc::now().naive_local();
+        for report_job in self.database.fail_stale_report_jobs(now)? {
+            error!(
+                "Report job {} for wallet {} stopped progressing, failed it",
+                report_job.id, report_job.wallet_addr
+            );
+        }
+        Ok(())
+    }
+
+    async fn enqueue_stale_entries(&mut self) -> Result<()> {
+        let now = Utc::now().naive_local();
+        let stale_entries = self.database.get_stale_watchlist_entries(now)?;
+        info!("Found {} stale watchlist entries", stale_entries.len());
+
+        // a wallet watched by several watchlists only needs a single report
+        let mut enqueued_wallets: HashMap<String, bool> = HashMap::new();
+        for entry in stale_entries {
+            let enqueued = match enqueued_wallets.get(&entry.wallet_addr) {
+                Some(enqueued) => *enqueued,
+                None => {
+                    let enqueued = match self.enqueue_report(&entry.wallet_addr).await {
+                        Ok(()) => true,
+                        Err(e) => {
+                            error!(
+                                "Failed to schedule report for wallet address {}: {:?}",
+                                entry.wallet_addr, e
+                            );
+                            false
+                        }
+                    };
+                    enqueued_wallets.insert(entry.wallet_addr.clone(), enqueued);
+                    enqueued
+                }
+            };
+            // entries of a wallet which failed to enqueue are retried at the next poll
+            if enqueued {
+                self.database.mark_watchlist_entry_enqueued(&entry, now)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts a report for the wallet, unless one requested by a user is already in flight
+    async fn enqueue_report(&mut self, wallet_addr: &str) -> Result<()> {
+        if self
+            .database
+            .get_in_flight_report_job(wallet_addr)?
+            .is_some()
+        {
+            info!(
+                "Skipping scheduled report for wallet address: {}, a report is already in flight",
+                wallet_addr
+            );
+            return Ok(());
+        }
+        let job = WalletReportJob {
+            report_id: Uuid::new_v4(),
+            wallet_addr: wallet_addr.to_string(),
+            user_id: None,
+        };
+        if !self.database.insert_report_job(&ReportJob::new(
+            job.report_id,
+            job.wallet_addr.clone(),
+            job.user_id,
+        ))? {
+            return Ok(());
+        }
+        info!(
+            "Enqueuing scheduled wallet report job with report ID: {} for wallet address: {}",
+            job.report_id, job.wallet_addr
+        );
+        let report_id = job.report_id;
+        if let Err(e) = self.job_producer.enqueue_job(job).await {
+            // the queued job would otherwise block reports of the wallet until it times out
+            if let Err(err) = self.database.update_report_job_state(
+                report_id,
+                ReportJobState::Failed,
+                Some(format!("Unable to enqueue job: {}", e)),
+            ) {
+                error!(
+                    "Failed to mark report job {} as failed: {:?}",
+                    report_id, err
+                );
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+}
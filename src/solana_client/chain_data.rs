@@ -0,0 +1,637 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use futures::{future::BoxFuture, FutureExt};
+use log::{debug, error, info};
+use serde::{de::DeserializeOwned, Serialize};
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_response::{
+        RpcConfirmedTransactionStatusWithSignature, RpcKeyedAccount, RpcPrioritizationFee,
+    },
+};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction};
+
+use super::{solana_client::SolanaClient, transaction_cache::TransactionCache};
+
+const ACCOUNT_BALANCE: &str = "get_account_balance";
+const ACCOUNT_INFO: &str = "get_account_info";
+const TRANSACTION_HISTORY: &str = "get_transaction_history";
+const OLDEST_SIGNATURE: &str = "get_oldest_signature";
+pub(crate) const FIRST_SIGNATURES: &str = "get_first_signatures";
+const TOKEN_ACCOUNTS: &str = "get_token_accounts";
+const PRIORITIZATION_FEES: &str = "get_recent_prioritization_fees";
+pub(crate) const TRANSACTION: &str = "get_transaction";
+
+/// The on-chain data a wallet report is computed from
+pub trait ChainDataSource: Send + Sync {
+    fn get_account_balance<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<u64, ClientError>>;
+
+    /// `None` if the account doesn't exist
+    fn get_account_info<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Option<Account>, ClientError>>;
+
+    /// The wallet's signatures, newest first
+    fn get_transaction_history<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Vec<RpcConfirmedTransactionStatusWithSignature>, ClientError>>;
+
+    /// The wallet's first-ever signature, `before` itself if nothing is older. `None` if the
+    /// wallet has no signatures or its history is too long to walk to the first one
+    fn get_oldest_signature<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+        before: Option<&'a RpcConfirmedTransactionStatusWithSignature>,
+    ) -> BoxFuture<'a, Result<Option<RpcConfirmedTransactionStatusWithSignature>, ClientError>>;
+
+    /// The wallet's oldest page of signatures, oldest first, `None` if its history is longer
+    /// than `max_pages` pages
+    fn get_first_signatures<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+        max_pages: usize,
+    ) -> BoxFuture<'a, Result<Option<Vec<RpcConfirmedTransactionStatusWithSignature>>, ClientError>>;
+
+    fn get_token_accounts<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Vec<RpcKeyedAccount>, ClientError>>;
+
+    fn get_recent_prioritization_fees<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Vec<RpcPrioritizationFee>, ClientError>>;
+
+    /// The confirmed transactions of the signatures in the same order, skipping those which
+    /// couldn't be fetched
+    fn batch_process_transactions(
+        &self,
+        signatures: Vec<RpcConfirmedTransactionStatusWithSignature>,
+    ) -> BoxFuture<'_, Vec<EncodedConfirmedTransactionWithStatusMeta>>;
+}
+
+impl ChainDataSource for SolanaClient {
+    fn get_account_balance<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<u64, ClientError>> {
+        SolanaClient::get_account_balance(self, pub_key).boxed()
+    }
+
+    fn get_account_info<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Option<Account>, ClientError>> {
+        SolanaClient::get_account_info(self, pub_key).boxed()
+    }
+
+    fn get_transaction_history<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Vec<RpcConfirmedTransactionStatusWithSignature>, ClientError>> {
+        SolanaClient::get_transaction_history(self, pub_key).boxed()
+    }
+
+    fn get_oldest_signature<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+        before: Option<&'a RpcConfirmedTransactionStatusWithSignature>,
+    ) -> BoxFuture<'a, Result<Option<RpcConfirmedTransactionStatusWithSignature>, ClientError>>
+    {
+        SolanaClient::get_oldest_signature(self, pub_key, before).boxed()
+    }
+
+    fn get_first_signatures<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+        max_pages: usize,
+    ) -> BoxFuture<'a, Result<Option<Vec<RpcConfirmedTransactionStatusWithSignature>>, ClientError>>
+    {
+        SolanaClient::get_first_signatures(self, pub_key, max_pages).boxed()
+    }
+
+    fn get_token_accounts<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Vec<RpcKeyedAccount>, ClientError>> {
+        SolanaClient::get_token_accounts(self, pub_key).boxed()
+    }
+
+    fn get_recent_prioritization_fees<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Vec<RpcPrioritizationFee>, ClientError>> {
+        SolanaClient::get_recent_prioritization_fees(self, pub_key).boxed()
+    }
+
+    fn batch_process_transactions(
+        &self,
+        signatures: Vec<RpcConfirmedTransactionStatusWithSignature>,
+    ) -> BoxFuture<'_, Vec<EncodedConfirmedTransactionWithStatusMeta>> {
+        SolanaClient::batch_process_transactions(self, signatures).boxed()
+    }
+}
+
+fn fixture_path(dir: &Path, method: &str, key: &str) -> PathBuf {
+    dir.join(method).join(format!("{}.json", key))
+}
+
+/// Writes a response as `<dir>/<method>/<key>.json`
+pub fn write_fixture<T: Serialize>(dir: &Path, method: &str, key: &str, value: &T) -> Result<()> {
+    let path = fixture_path(dir, method, key);
+    fs::create_dir_all(dir.join(method))?;
+    fs::write(&path, serde_json::to_vec_pretty(value)?)?;
+    debug!("Recorded fixture {}", path.display());
+    Ok(())
+}
+
+/// Signature the transaction was fetched by, its first signature
+pub(crate) fn transaction_signature(
+    transaction: &EncodedConfirmedTransactionWithStatusMeta,
+) -> Option<String> {
+    match &transaction.transaction.transaction {
+        EncodedTransaction::Json(ui_transaction) => ui_transaction.signatures.first().cloned(),
+        encoded_transaction => encoded_transaction
+            .decode()
+            .and_then(|transaction| transaction.signatures.first().map(ToString::to_string)),
+    }
+}
+
+/// Serves responses previously recorded by a `RecordingDataSource`, for running the
+/// pipeline offline and deterministically
+pub struct FixtureDataSource {
+    dir: PathBuf,
+}
+
+impl FixtureDataSource {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    // fixtures fail the same way the RPC client does, with the trait's `ClientError`
+    #[allow(clippy::result_large_err)]
+    fn read<T: DeserializeOwned>(&self, method: &str, key: &str) -> Result<T, ClientError> {
+        let path = fixture_path(&self.dir, method, key);
+        let contents = fs::read(&path).map_err(|e| {
+            ClientErrorKind::Custom(format!("No fixture at {}: {}", path.display(), e))
+        })?;
+        serde_json::from_slice(&contents).map_err(|e| {
+            ClientErrorKind::Custom(format!("Malformed fixture {}: {}", path.display(), e)).into()
+        })
+    }
+}
+
+impl ChainDataSource for FixtureDataSource {
+    fn get_account_balance<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<u64, ClientError>> {
+        async move { self.read(ACCOUNT_BALANCE, &pub_key.to_string()) }.boxed()
+    }
+
+    fn get_account_info<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Option<Account>, ClientError>> {
+        async move { self.read(ACCOUNT_INFO, &pub_key.to_string()) }.boxed()
+    }
+
+    fn get_transaction_history<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Vec<RpcConfirmedTransactionStatusWithSignature>, ClientError>> {
+        async move { self.read(TRANSACTION_HISTORY, &pub_key.to_string()) }.boxed()
+    }
+
+    /// Recorded per wallet, a fixture is only ever replayed from the same history
+    fn get_oldest_signature<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+        _before: Option<&'a RpcConfirmedTransactionStatusWithSignature>,
+    ) -> BoxFuture<'a, Result<Option<RpcConfirmedTransactionStatusWithSignature>, ClientError>>
+    {
+        async move { self.read(OLDEST_SIGNATURE, &pub_key.to_string()) }.boxed()
+    }
+
+    fn get_first_signatures<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+        _max_pages: usize,
+    ) -> BoxFuture<'a, Result<Option<Vec<RpcConfirmedTransactionStatusWithSignature>>, ClientError>>
+    {
+        async move { self.read(FIRST_SIGNATURES, &pub_key.to_string()) }.boxed()
+    }
+
+    fn get_token_accounts<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Vec<RpcKeyedAccount>, ClientError>> {
+        async move { self.read(TOKEN_ACCOUNTS, &pub_key.to_string()) }.boxed()
+    }
+
+    fn get_recent_prioritization_fees<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Vec<RpcPrioritizationFee>, ClientError>> {
+        async move { self.read(PRIORITIZATION_FEES, &pub_key.to_string()) }.boxed()
+    }
+
+    fn batch_process_transactions(
+        &self,
+        signatures: Vec<RpcConfirmedTransactionStatusWithSignature>,
+    ) -> BoxFuture<'_, Vec<EncodedConfirmedTransactionWithStatusMeta>> {
+        async move {
+            signatures
+                .iter()
+                .filter_map(|sig_info| {
+                    self.read(TRANSACTION, &sig_info.signature)
+                        .map_err(|e| debug!("Skipping transaction without fixture: {}", e))
+                        .ok()
+                })
+                .collect()
+        }
+        .boxed()
+    }
+}
+
+/// Fetches from the live client and records every successful response as a fixture
+pub struct RecordingDataSource {
+    client: SolanaClient,
+    dir: PathBuf,
+}
+
+impl RecordingDataSource {
+    pub fn new(client: SolanaClient, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            client,
+            dir: dir.into(),
+        }
+    }
+
+    /// Recording is a side effect, failing to write a fixture shouldn't fail the fetch
+    fn record<T: Serialize>(&self, method: &str, key: &str, result: &Result<T, ClientError>) {
+        if let Ok(value) = result {
+            if let Err(e) = write_fixture(&self.dir, method, key, value) {
+                error!("Failed to record {} fixture for {}: {:?}", method, key, e);
+            }
+        }
+    }
+}
+
+impl ChainDataSource for RecordingDataSource {
+    fn get_account_balance<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<u64, ClientError>> {
+        async move {
+            let result = self.client.get_account_balance(pub_key).await;
+            self.record(ACCOUNT_BALANCE, &pub_key.to_string(), &result);
+            result
+        }
+        .boxed()
+    }
+
+    fn get_account_info<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Option<Account>, ClientError>> {
+        async move {
+            let result = self.client.get_account_info(pub_key).await;
+            self.record(ACCOUNT_INFO, &pub_key.to_string(), &result);
+            result
+        }
+        .boxed()
+    }
+
+    fn get_transaction_history<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Vec<RpcConfirmedTransactionStatusWithSignature>, ClientError>> {
+        async move {
+            let result = self.client.get_transaction_history(pub_key).await;
+            self.record(TRANSACTION_HISTORY, &pub_key.to_string(), &result);
+            result
+        }
+        .boxed()
+    }
+
+    fn get_oldest_signature<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+        before: Option<&'a RpcConfirmedTransactionStatusWithSignature>,
+    ) -> BoxFuture<'a, Result<Option<RpcConfirmedTransactionStatusWithSignature>, ClientError>>
+    {
+        async move {
+            let result = self.client.get_oldest_signature(pub_key, before).await;
+            self.record(OLDEST_SIGNATURE, &pub_key.to_string(), &result);
+            result
+        }
+        .boxed()
+    }
+
+    fn get_first_signatures<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+        max_pages: usize,
+    ) -> BoxFuture<'a, Result<Option<Vec<RpcConfirmedTransactionStatusWithSignature>>, ClientError>>
+    {
+        async move {
+            let result = self.client.get_first_signatures(pub_key, max_pages).await;
+            self.record(FIRST_SIGNATURES, &pub_key.to_string(), &result);
+            result
+        }
+        .boxed()
+    }
+
+    fn get_token_accounts<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Vec<RpcKeyedAccount>, ClientError>> {
+        async move {
+            let result = self.client.get_token_accounts(pub_key).await;
+            self.record(TOKEN_ACCOUNTS, &pub_key.to_string(), &result);
+            result
+        }
+        .boxed()
+    }
+
+    fn get_recent_prioritization_fees<'a>(
+        &'a self,
+        pub_key: &'a Pubkey,
+    ) -> BoxFuture<'a, Result<Vec<RpcPrioritizationFee>, ClientError>> {
+        async move {
+            let result = self.client.get_recent_prioritization_fees(pub_key).await;
+            self.record(PRIORITIZATION_FEES, &pub_key.to_string(), &result);
+            result
+        }
+        .boxed()
+    }
+
+    fn batch_process_transactions(
+        &self,
+        signatures: Vec<RpcConfirmedTransactionStatusWithSignature>,
+    ) -> BoxFuture<'_, Vec<EncodedConfirmedTransactionWithStatusMeta>> {
+        async move {
+            let transactions = self.client.batch_process_transactions(signatures).await;
+            for transaction in &transactions {
+                match transaction_signature(transaction) {
+                    Some(signature) => self.record(TRANSACTION, &signature, &Ok(transaction)),
+                    None => error!("Can't record transaction without a signature"),
+                }
+            }
+            transactions
+        }
+        .boxed()
+    }
+}
+
+/// Picks the data source from `CHAIN_DATA_MODE`: `live` (default) fetches from RPC through
+/// the transaction cache, `record` additionally writes every response to
+/// `CHAIN_DATA_FIXTURES_DIR` and `fixture` serves the responses recorded there
+pub fn chain_data_source_from_env() -> Result<Box<dyn ChainDataSource>> {
+    let mode = env::var("CHAIN_DATA_MODE").unwrap_or_else(|_| "live".to_string());
+    let fixtures_dir = || {
+        env::var("CHAIN_DATA_FIXTURES_DIR")
+            .map_err(|_| anyhow!("CHAIN_DATA_FIXTURES_DIR should be set in {} mode", mode))
+    };
+    info!("Using {} chain data", mode);
+    match mode.as_str() {
+        "live" => Ok(Box::new(
+            SolanaClient::new().with_transaction_cache(TransactionCache::connect()?),
+        )),
+        "record" => Ok(Box::new(RecordingDataSource::new(
+            SolanaClient::new(),
+            fixtures_dir()?,
+        ))),
+        "fixture" => Ok(Box::new(FixtureDataSource::new(fixtures_dir()?))),
+        _ => Err(anyhow!("Unknown CHAIN_DATA_MODE: {}", mode)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::{
+        database::models::RatingClassification,
+        reputation::{
+            associates::{KnownCreditedAssociates, KnownDiscreditedAssociates},
+            funding_source::FundingSource,
+            indicator_registry::{Indicator, IndicatorContext, IndicatorRegistry},
+            indicators::WalletAge,
+            reputation::{PenaltySeverity, Reputation},
+            scoring_policy::{ScoringPolicy, SeverityDeductions},
+        },
+        test_utils::test_utils::create_mock_signature,
+        wallet::{
+            address::InvalidWalletAddress,
+            wallet::{Wallet, WalletError},
+        },
+    };
+
+    use super::*;
+
+    const TEST_WALLET_ADDR: &str = "CKs1E69a2e9TmH4mKKLrXFF8kD3ZnwKjoEuXa6sz9WqX";
+
+    /// Fixtures of a wallet holding 5 SOL with a short transaction history
+    fn write_wallet_fixtures(dir: &Path) {
+        write_fixture(dir, ACCOUNT_BALANCE, TEST_WALLET_ADDR, &5_000_000_000u64).unwrap();
+        let account = Account {
+            lamports: 5_000_000_000,
+            ..Default::default()
+        };
+        write_fixture(dir, ACCOUNT_INFO, TEST_WALLET_ADDR, &Some(account)).unwrap();
+        let history: Vec<_> = [1_700_000_300, 1_700_000_200, 1_700_000_100]
+            .into_iter()
+            .map(|block_time| {
+                create_mock_signature(&format!("signature_{}", block_time), Some(block_time))
+            })
+            .collect();
+        write_fixture(dir, TRANSACTION_HISTORY, TEST_WALLET_ADDR, &history).unwrap();
+        write_fixture(
+            dir,
+            OLDEST_SIGNATURE,
+            TEST_WALLET_ADDR,
+            &Some(create_mock_signature(
+                "signature_1600000000",
+                Some(1_600_000_000),
+            )),
+        )
+        .unwrap();
+        write_fixture(
+            dir,
+            TOKEN_ACCOUNTS,
+            TEST_WALLET_ADDR,
+            &Vec::<RpcKeyedAccount>::new(),
+        )
+        .unwrap();
+        write_fixture(
+            dir,
+            PRIORITIZATION_FEES,
+            TEST_WALLET_ADDR,
+            &Vec::<RpcPrioritizationFee>::new(),
+        )
+        .unwrap();
+    }
+
+    async fn score_wallet(source: &dyn ChainDataSource, policy: &ScoringPolicy) -> Reputation {
+        let wallet = Wallet::new(TEST_WALLET_ADDR, source).await.unwrap();
+        let transactions = source
+            .batch_process_transactions(wallet.transaction_history.clone())
+            .await;
+        let context = IndicatorContext {
+            wallet: &wallet,
+            confirmed_transactions: &transactions,
+            discredited_associates: &KnownDiscreditedAssociates { wallets: vec![] },
+            credited_associates: &KnownCreditedAssociates { wallets: vec![] },
+            funding_source: &FundingSource::default(),
+            policy,
+        };
+        Reputation::new_from_wallet(
+            &context,
+            &IndicatorRegistry::from_policy(policy),
+            Uuid::nil(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_fixture_round_trip() {
+        let dir = env::temp_dir().join(format!("chain-data-{}", Uuid::new_v4()));
+        write_wallet_fixtures(&dir);
+        let source = FixtureDataSource::new(dir.clone());
+
+        let wallet = Wallet::new(TEST_WALLET_ADDR, &source).await.unwrap();
+        assert_eq!(wallet.account_balance, 5_000_000_000);
+        assert_eq!(wallet.transaction_history.len(), 3);
+        assert_eq!(wallet.first_seen, Some(1_600_000_000));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Scores the committed fixture wallet: funded with 201 SOL, then one transfer out and one
+    /// fee only transaction, all within two hours. The responses are in the recorded layout.
+    /// Deductions are lowered so the score doesn't clamp at zero and every penalty shows in it
+    #[tokio::test]
+    async fn test_pipeline_from_fixtures() {
+        let source = FixtureDataSource::new(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/chain_data"),
+        );
+        let policy = ScoringPolicy {
+            deductions: SeverityDeductions {
+                high: 50,
+                medium: 30,
+                low: 10,
+                bonus: 10,
+            },
+            ..Default::default()
+        };
+
+        let reputation = score_wallet(&source, &policy).await;
+        let penalties: Vec<(&str, PenaltySeverity)> = reputation
+            .indicators
+            .iter()
+            .filter(|indicator| indicator.penalty.severity != PenaltySeverity::None)
+            .map(|indicator| (indicator.name.as_str(), indicator.penalty.severity))
+            .collect();
+        assert_eq!(
+            penalties,
+            vec![
+                ("tx_per_hour", PenaltySeverity::Low),
+                ("days_since_last_block", PenaltySeverity::High),
+                ("avg_prio_fee", PenaltySeverity::High),
+                ("transactions_with_new_wallets", PenaltySeverity::High),
+            ]
+        );
+        assert_eq!(reputation.rating_score, 870);
+        assert_eq!(reputation.rating_classification, RatingClassification::AA);
+    }
+
+    #[tokio::test]
+    async fn test_executable_account_is_invalid_address() {
+        let dir = env::temp_dir().join(format!("chain-data-{}", Uuid::new_v4()));
+        write_wallet_fixtures(&dir);
+        let program = Account {
+            lamports: 1_141_440,
+            executable: true,
+            ..Default::default()
+        };
+        write_fixture(&dir, ACCOUNT_INFO, TEST_WALLET_ADDR, &Some(program)).unwrap();
+        let source = FixtureDataSource::new(dir.clone());
+
+        let result = Wallet::new(TEST_WALLET_ADDR, &source).await;
+        assert!(matches!(
+            result,
+            Err(WalletError::InvalidAddress(
+                InvalidWalletAddress::Executable
+            ))
+        ));
+        assert!(result.unwrap_err().is_permanent());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_oldest_signature_walk_leaves_age_unknown() {
+        let dir = env::temp_dir().join(format!("chain-data-{}", Uuid::new_v4()));
+        write_wallet_fixtures(&dir);
+        fs::remove_dir_all(dir.join(OLDEST_SIGNATURE)).unwrap();
+        let source = FixtureDataSource::new(dir.clone());
+
+        let wallet = Wallet::new(TEST_WALLET_ADDR, &source).await.unwrap();
+        assert_eq!(wallet.transaction_history.len(), 3);
+        assert_eq!(wallet.first_seen, None);
+        assert!(wallet.first_transaction.is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_truncated_oldest_signature_walk_leaves_age_unknown() {
+        let dir = env::temp_dir().join(format!("chain-data-{}", Uuid::new_v4()));
+        write_wallet_fixtures(&dir);
+        // recorded from a walk which gave up before reaching the wallet's first page
+        write_fixture(
+            &dir,
+            OLDEST_SIGNATURE,
+            TEST_WALLET_ADDR,
+            &None::<RpcConfirmedTransactionStatusWithSignature>,
+        )
+        .unwrap();
+        let source = FixtureDataSource::new(dir.clone());
+
+        let wallet = Wallet::new(TEST_WALLET_ADDR, &source).await.unwrap();
+        assert_eq!(wallet.first_seen, None);
+        assert!(wallet.first_transaction.is_none());
+
+        let reputation = score_wallet(&source, &ScoringPolicy::default()).await;
+        let wallet_age = reputation
+            .indicators
+            .iter()
+            .find(|indicator| indicator.name == WalletAge::NAME)
+            .unwrap();
+        assert_eq!(wallet_age.penalty.severity, PenaltySeverity::None);
+        assert_eq!(
+            wallet_age.penalty.reasoning,
+            vec!["Wallet age unknown".to_string()]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_missing_fixture_is_rpc_error() {
+        let source = FixtureDataSource::new(env::temp_dir().join("chain-data-missing"));
+        let result = Wallet::new(TEST_WALLET_ADDR, &source).await;
+        assert!(matches!(result, Err(WalletError::Rpc(_))));
+    }
+}
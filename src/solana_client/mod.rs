@@ -1 +1,4 @@
+pub mod chain_data;
+pub mod rpc_pool;
 pub mod solana_client;
+pub mod transaction_cache;
@@ -0,0 +1,287 @@
+use std::{
+    env,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+use rand::{rng, Rng};
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    nonblocking::rpc_client::RpcClient,
+    rpc_request::RpcError,
+};
+use tokio::sync::Semaphore;
+
+const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 20;
+const DEFAULT_MAX_RETRIES: u32 = 4;
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(500);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+/// Consecutive retryable failures after which an endpoint is skipped during its cooldown
+const FAILURES_BEFORE_UNHEALTHY: u32 = 3;
+const UNHEALTHY_COOLDOWN: Duration = Duration::from_secs(30);
+/// JSON-RPC error codes returned by nodes that are overloaded or lagging behind
+const RETRYABLE_RPC_ERROR_CODES: [i64; 2] = [-32005, 429];
+
+#[derive(Debug, Clone)]
+pub struct RpcPoolConfig {
+    /// Endpoints in order of preference, later ones are only used for failover
+    pub urls: Vec<String>,
+    /// Maximum number of requests in flight across all endpoints
+    pub max_concurrent_requests: usize,
+    /// Retries of a failed request, on top of the first attempt
+    pub max_retries: u32,
+}
+
+impl RpcPoolConfig {
+    /// Reads the comma separated `RPC_URLS`, falling back to `RPC_URL`, along with
+    /// `RPC_MAX_CONCURRENT_REQUESTS` and `RPC_MAX_RETRIES`
+    pub fn from_env() -> Self {
+        let urls = env::var("RPC_URLS")
+            .or_else(|_| env::var("RPC_URL"))
+            .expect("rpc url should be set");
+        Self {
+            urls: urls
+                .split(',')
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .map(str::to_string)
+                .collect(),
+            max_concurrent_requests: env::var("RPC_MAX_CONCURRENT_REQUESTS")
+                .ok()
+                .and_then(|limit| limit.parse().ok())
+                .filter(|&limit| limit > 0)
+                .unwrap_or(DEFAULT_MAX_CONCURRENT_REQUESTS),
+            max_retries: env::var("RPC_MAX_RETRIES")
+                .ok()
+                .and_then(|retries| retries.parse().ok())
+                .unwrap_or(DEFAULT_MAX_RETRIES),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct EndpointHealth {
+    consecutive_failures: u32,
+    unhealthy_until: Option<Instant>,
+}
+
+struct RpcEndpoint {
+    url: String,
+    client: Arc<RpcClient>,
+    health: Mutex<EndpointHealth>,
+}
+
+impl RpcEndpoint {
+    fn is_healthy(&self, now: Instant) -> bool {
+        let health = self.health.lock().expect("Endpoint health lock poisoned");
+        health.unhealthy_until.is_none_or(|until| until <= now)
+    }
+
+    fn record_success(&self) {
+        let mut health = self.health.lock().expect("Endpoint health lock poisoned");
+        if health.unhealthy_until.is_some() {
+            info!("RPC endpoint {} recovered", self.url);
+        }
+        *health = EndpointHealth::default();
+    }
+
+    fn record_failure(&self, now: Instant) {
+        let mut health = self.health.lock().expect("Endpoint health lock poisoned");
+        health.consecutive_failures += 1;
+        if health.consecutive_failures >= FAILURES_BEFORE_UNHEALTHY {
+            warn!(
+                "RPC endpoint {} marked unhealthy after {} consecutive failures",
+                self.url, health.consecutive_failures
+            );
+            health.unhealthy_until = Some(now + UNHEALTHY_COOLDOWN);
+        }
+    }
+}
+
+/// Whether a failed request may succeed when retried, possibly against another endpoint
+pub fn is_retryable(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::Io(_) => true,
+        ClientErrorKind::Reqwest(e) => {
+            e.is_timeout()
+                || e.is_connect()
+                || e.status()
+                    .is_some_and(|status| status.as_u16() == 429 || status.is_server_error())
+        }
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            RETRYABLE_RPC_ERROR_CODES.contains(code)
+        }
+        _ => false,
+    }
+}
+
+/// Exponential backoff before the given retry, starting at 1
+fn retry_delay(retry: u32) -> Duration {
+    INITIAL_RETRY_DELAY
+        .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+        .min(MAX_RETRY_DELAY)
+}
+
+/// Spreads requests over a list of RPC endpoints, limiting concurrency, retrying
+/// rate limited and failed requests with backoff and failing over unhealthy endpoints
+pub struct RpcPool {
+    endpoints: Vec<RpcEndpoint>,
+    permits: Semaphore,
+    max_retries: u32,
+}
+
+impl RpcPool {
+    pub fn new(config: RpcPoolConfig) -> Self {
+        assert!(
+            !config.urls.is_empty(),
+            "at least one rpc url should be set"
+        );
+        Self {
+            endpoints: config
+                .urls
+                .into_iter()
+                .map(|url| RpcEndpoint {
+                    client: Arc::new(RpcClient::new(url.clone())),
+                    url,
+                    health: Mutex::new(EndpointHealth::default()),
+                })
+                .collect(),
+            permits: Semaphore::new(config.max_concurrent_requests),
+            max_retries: config.max_retries,
+        }
+    }
+
+    /// Picks the endpoint for an attempt, rotating through the healthy endpoints on
+    /// retries, or through all of them when none are healthy
+    fn select_endpoint(&self, attempt: u32, now: Instant) -> &RpcEndpoint {
+        let healthy: Vec<_> = self
+            .endpoints
+            .iter()
+            .filter(|endpoint| endpoint.is_healthy(now))
+            .collect();
+        if healthy.is_empty() {
+            &self.endpoints[attempt as usize % self.endpoints.len()]
+        } else {
+            healthy[attempt as usize % healthy.len()]
+        }
+    }
+
+    /// Sends a request, retrying it against the next healthy endpoint while it fails retryably
+    pub async fn call<T, F, Fut>(&self, request: F) -> Result<T, ClientError>
+    where
+        F: Fn(Arc<RpcClient>) -> Fut,
+        Fut: Future<Output = Result<T, ClientError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let endpoint = self.select_endpoint(attempt, Instant::now());
+            let result = {
+                let _permit = self
+                    .permits
+                    .acquire()
+                    .await
+                    .expect("Rpc permits should never be closed");
+                request(endpoint.client.clone()).await
+            };
+
+            match result {
+                Ok(value) => {
+                    endpoint.record_success();
+                    return Ok(value);
+                }
+                Err(e) if is_retryable(&e) && attempt < self.max_retries => {
+                    endpoint.record_failure(Instant::now());
+                    attempt += 1;
+                    let delay = retry_delay(attempt);
+                    // jitter keeps concurrent requests from retrying in lockstep
+                    let jitter = rng().random_range(0..=delay.as_millis() as u64 / 2);
+                    warn!(
+                        "RPC request to {} failed, retry {} of {} in {:?}: {}",
+                        endpoint.url, attempt, self.max_retries, delay, e
+                    );
+                    tokio::time::sleep(delay + Duration::from_millis(jitter)).await;
+                }
+                Err(e) => {
+                    if is_retryable(&e) {
+                        endpoint.record_failure(Instant::now());
+                    }
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mock_pool() -> RpcPool {
+        RpcPool::new(RpcPoolConfig {
+            urls: vec![
+                "http://primary.invalid".to_string(),
+                "http://fallback.invalid".to_string(),
+            ],
+            max_concurrent_requests: 1,
+            max_retries: 2,
+        })
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), Duration::from_millis(500));
+        assert_eq!(retry_delay(2), Duration::from_secs(1));
+        assert_eq!(retry_delay(4), Duration::from_secs(4));
+        assert_eq!(retry_delay(10), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(u32::MAX), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(is_retryable(
+            &std::io::Error::other("connection reset").into()
+        ));
+        assert!(is_retryable(
+            &ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                code: -32005,
+                message: "Node is behind".to_string(),
+                data: solana_client::rpc_request::RpcResponseErrorData::Empty,
+            })
+            .into()
+        ));
+        assert!(!is_retryable(
+            &ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                code: -32602,
+                message: "Invalid params".to_string(),
+                data: solana_client::rpc_request::RpcResponseErrorData::Empty,
+            })
+            .into()
+        ));
+        assert!(!is_retryable(
+            &ClientErrorKind::Custom("invalid signature".to_string()).into()
+        ));
+    }
+
+    #[test]
+    fn test_select_endpoint_fails_over_unhealthy_endpoints() {
+        let pool = mock_pool();
+        let now = Instant::now();
+        assert_eq!(pool.select_endpoint(0, now).url, "http://primary.invalid");
+        assert_eq!(pool.select_endpoint(1, now).url, "http://fallback.invalid");
+
+        for _ in 0..FAILURES_BEFORE_UNHEALTHY {
+            pool.endpoints[0].record_failure(now);
+        }
+        assert_eq!(pool.select_endpoint(0, now).url, "http://fallback.invalid");
+        assert_eq!(pool.select_endpoint(1, now).url, "http://fallback.invalid");
+
+        // the primary is used again once its cooldown elapsed
+        let later = now + UNHEALTHY_COOLDOWN;
+        assert_eq!(pool.select_endpoint(0, later).url, "http://primary.invalid");
+
+        pool.endpoints[0].record_success();
+        assert!(pool.endpoints[0].is_healthy(now));
+    }
+}
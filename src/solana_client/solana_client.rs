@@ -1,52 +1,338 @@
 use futures::future::join_all;
+use log::{debug, info, warn};
 use solana_client::{
     client_error::ClientError,
-    nonblocking::rpc_client::RpcClient,
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_request::TokenAccountsFilter,
     rpc_response::{
         RpcConfirmedTransactionStatusWithSignature, RpcKeyedAccount, RpcPrioritizationFee,
     },
 };
 use solana_sdk::{account::Account, pubkey::Pubkey, signature::Signature};
 use solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, UiTransactionEncoding};
-use std::{env, str::FromStr};
+use std::{
+    collections::HashMap,
+    env,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{
+    rpc_pool::{RpcPool, RpcPoolConfig},
+    transaction_cache::{is_cacheable, TransactionCache},
+};
 
 const CHUNK_SIZE: usize = 100;
+const SPL_TOKEN_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+const TOKEN_2022_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+/// Maximum number of signatures returned by a single `getSignaturesForAddress` call
+const SIGNATURES_PAGE_LIMIT: usize = 1000;
+const DEFAULT_MAX_HISTORY_DEPTH: usize = 10_000;
+/// Each confirmed transaction is a `getTransaction` call of its own
+const DEFAULT_MAX_CONFIRMED_TRANSACTIONS: usize = 1000;
+/// Bounds the walk to a wallet's first transaction, very active wallets are old anyway
+const MAX_OLDEST_SIGNATURE_PAGES: usize = 100;
+
+/// Bounds how far back the transaction history of a wallet is fetched
+#[derive(Debug, Clone)]
+pub struct TransactionHistoryConfig {
+    /// Maximum number of signatures to fetch
+    pub max_depth: usize,
+    /// Only fetch signatures from the last n days, when set
+    pub time_window_days: Option<u64>,
+    /// Maximum number of the fetched signatures whose full transactions are fetched, the most
+    /// recent ones first
+    pub max_confirmed_transactions: usize,
+}
+
+impl Default for TransactionHistoryConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_HISTORY_DEPTH,
+            time_window_days: None,
+            max_confirmed_transactions: DEFAULT_MAX_CONFIRMED_TRANSACTIONS,
+        }
+    }
+}
+
+impl TransactionHistoryConfig {
+    /// Reads `MAX_TRANSACTION_HISTORY_DEPTH`, `TRANSACTION_HISTORY_WINDOW_DAYS` and
+    /// `MAX_CONFIRMED_TRANSACTIONS`
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            max_depth: env::var("MAX_TRANSACTION_HISTORY_DEPTH")
+                .ok()
+                .and_then(|depth| depth.parse().ok())
+                .unwrap_or(default.max_depth),
+            time_window_days: env::var("TRANSACTION_HISTORY_WINDOW_DAYS")
+                .ok()
+                .and_then(|days| days.parse().ok())
+                .or(default.time_window_days),
+            max_confirmed_transactions: env::var("MAX_CONFIRMED_TRANSACTIONS")
+                .ok()
+                .and_then(|max| max.parse().ok())
+                .unwrap_or(default.max_confirmed_transactions),
+        }
+    }
+
+    /// Unix timestamp of the oldest block time inside the time window
+    fn cutoff_time(&self) -> Option<i64> {
+        self.time_window_days.map(|days| {
+            let current_time = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("Time went backwards")
+                .as_secs();
+            current_time.saturating_sub(days * 60 * 60 * 24) as i64
+        })
+    }
+}
+
+/// Drops signatures older than the cutoff, returning whether any were dropped
+fn truncate_to_cutoff(
+    page: &mut Vec<RpcConfirmedTransactionStatusWithSignature>,
+    cutoff_time: Option<i64>,
+) -> bool {
+    let Some(cutoff_time) = cutoff_time else {
+        return false;
+    };
+    let len = page.len();
+    // signatures are returned newest first, so everything after the first old one is old as well
+    if let Some(idx) = page
+        .iter()
+        .position(|sig| sig.block_time.is_some_and(|time| time < cutoff_time))
+    {
+        page.truncate(idx);
+    }
+    page.len() < len
+}
 
 pub struct SolanaClient {
-    client: RpcClient,
+    client: RpcPool,
+    history_config: TransactionHistoryConfig,
+    transaction_cache: Option<TransactionCache>,
 }
 
 impl SolanaClient {
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
-            client: RpcClient::new(env::var("RPC_URL").expect("rpc url should be set")),
+            client: RpcPool::new(RpcPoolConfig::from_env()),
+            history_config: TransactionHistoryConfig::from_env(),
+            transaction_cache: None,
         }
     }
 
+    /// Serves finalized transactions from the cache instead of refetching them
+    pub fn with_transaction_cache(mut self, transaction_cache: TransactionCache) -> Self {
+        self.transaction_cache = Some(transaction_cache);
+        self
+    }
+
     pub async fn get_account_balance(&self, pub_key: &Pubkey) -> Result<u64, ClientError> {
-        self.client.get_balance(pub_key).await
+        let pub_key = *pub_key;
+        self.client
+            .call(|client| async move { client.get_balance(&pub_key).await })
+            .await
     }
 
-    pub async fn get_account_info(&self, pub_key: &Pubkey) -> Result<Account, ClientError> {
-        self.client.get_account(pub_key).await
+    /// Fetches the account, `None` if it doesn't exist
+    pub async fn get_account_info(&self, pub_key: &Pubkey) -> Result<Option<Account>, ClientError> {
+        let pub_key = *pub_key;
+        self.client
+            .call(|client| async move {
+                client
+                    .get_account_with_commitment(&pub_key, client.commitment())
+                    .await
+                    .map(|response| response.value)
+            })
+            .await
     }
 
+    /// Fetches the wallet's signatures, newest first, bounded by the history config
     pub async fn get_transaction_history(
         &self,
         pub_key: &Pubkey,
     ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>, ClientError> {
-        self.client.get_signatures_for_address(pub_key).await
+        self.get_transaction_history_until(pub_key, None).await
     }
 
+    /// Pages backwards through the wallet's signatures using `before` cursors,
+    /// stopping at `until` (exclusive), the max depth or the start of the time window
+    pub async fn get_transaction_history_until(
+        &self,
+        pub_key: &Pubkey,
+        until: Option<Signature>,
+    ) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>, ClientError> {
+        let pub_key = *pub_key;
+        let max_depth = self.history_config.max_depth;
+        let cutoff_time = self.history_config.cutoff_time();
+        let mut history: Vec<RpcConfirmedTransactionStatusWithSignature> = Vec::new();
+        let mut before = None;
+
+        while history.len() < max_depth {
+            let limit = (max_depth - history.len()).min(SIGNATURES_PAGE_LIMIT);
+            let mut page = self
+                .client
+                .call(|client| async move {
+                    client
+                        .get_signatures_for_address_with_config(
+                            &pub_key,
+                            GetConfirmedSignaturesForAddress2Config {
+                                before,
+                                until,
+                                limit: Some(limit),
+                                commitment: None,
+                            },
+                        )
+                        .await
+                })
+                .await?;
+            debug!(
+                "Fetched page of {} signatures for {} before {:?}",
+                page.len(),
+                pub_key,
+                before
+            );
+
+            let is_last_page = page.len() < limit;
+            let reached_cutoff = truncate_to_cutoff(&mut page, cutoff_time);
+            before = page
+                .last()
+                .and_then(|sig| Signature::from_str(&sig.signature).ok());
+            history.extend(page);
+
+            if is_last_page || reached_cutoff || before.is_none() {
+                break;
+            }
+        }
+
+        info!(
+            "Fetched transaction history of {} signatures for {}",
+            history.len(),
+            pub_key
+        );
+        Ok(history)
+    }
+
+    /// Pages backwards through the wallet's signatures from `before`, for at most `max_pages`
+    /// pages. Returns the oldest non-empty page reached, newest first, and whether it is the
+    /// wallet's first page
+    async fn walk_signatures_back(
+        &self,
+        pub_key: Pubkey,
+        mut before: Option<Signature>,
+        max_pages: usize,
+    ) -> Result<(Vec<RpcConfirmedTransactionStatusWithSignature>, bool), ClientError> {
+        let mut oldest_page = Vec::new();
+
+        for _ in 0..max_pages {
+            let page = self
+                .client
+                .call(|client| async move {
+                    client
+                        .get_signatures_for_address_with_config(
+                            &pub_key,
+                            GetConfirmedSignaturesForAddress2Config {
+                                before,
+                                until: None,
+                                limit: Some(SIGNATURES_PAGE_LIMIT),
+                                commitment: None,
+                            },
+                        )
+                        .await
+                })
+                .await?;
+            let is_last_page = page.len() < SIGNATURES_PAGE_LIMIT;
+            if !page.is_empty() {
+                before = page
+                    .last()
+                    .and_then(|sig| Signature::from_str(&sig.signature).ok());
+                oldest_page = page;
+            }
+            if is_last_page {
+                return Ok((oldest_page, true));
+            }
+            if before.is_none() {
+                break;
+            }
+        }
+        Ok((oldest_page, false))
+    }
+
+    /// Walks the wallet's signatures backwards from `before` to find its first-ever transaction,
+    /// which is `before` itself if there is nothing older. Gives up after
+    /// `MAX_OLDEST_SIGNATURE_PAGES` pages, in which case `None` is returned as the age is unknown
+    pub async fn get_oldest_signature(
+        &self,
+        pub_key: &Pubkey,
+        before: Option<&RpcConfirmedTransactionStatusWithSignature>,
+    ) -> Result<Option<RpcConfirmedTransactionStatusWithSignature>, ClientError> {
+        let before_signature = before.and_then(|sig| Signature::from_str(&sig.signature).ok());
+        let (oldest_page, reached_first_page) = self
+            .walk_signatures_back(*pub_key, before_signature, MAX_OLDEST_SIGNATURE_PAGES)
+            .await?;
+        if !reached_first_page {
+            warn!(
+                "Stopped looking for the first transaction of {} after {} pages, its age is unknown",
+                pub_key, MAX_OLDEST_SIGNATURE_PAGES
+            );
+            return Ok(None);
+        }
+        Ok(oldest_page.into_iter().last().or_else(|| before.cloned()))
+    }
+
+    /// Fetches the wallet's oldest page of signatures, oldest first. `None` if the history is
+    /// longer than `max_pages` pages, so busy wallets don't cost a walk through all of it
+    pub async fn get_first_signatures(
+        &self,
+        pub_key: &Pubkey,
+        max_pages: usize,
+    ) -> Result<Option<Vec<RpcConfirmedTransactionStatusWithSignature>>, ClientError> {
+        let (mut oldest_page, reached_first_page) =
+            self.walk_signatures_back(*pub_key, None, max_pages).await?;
+        if !reached_first_page {
+            debug!(
+                "History of {} is longer than {} pages, not fetching its first signatures",
+                pub_key, max_pages
+            );
+            return Ok(None);
+        }
+        oldest_page.reverse();
+        Ok(Some(oldest_page))
+    }
+
+    /// Fetches the wallet's token accounts of both the SPL Token and Token-2022 programs
     pub async fn get_token_accounts(
         &self,
         pub_key: &Pubkey,
     ) -> Result<Vec<RpcKeyedAccount>, ClientError> {
+        let (token_accounts, token_2022_accounts) = tokio::join!(
+            self.get_program_token_accounts(pub_key, SPL_TOKEN_PROGRAM_ID),
+            self.get_program_token_accounts(pub_key, TOKEN_2022_PROGRAM_ID),
+        );
+        let mut token_accounts = token_accounts?;
+        token_accounts.extend(token_2022_accounts?);
+        Ok(token_accounts)
+    }
+
+    async fn get_program_token_accounts(
+        &self,
+        pub_key: &Pubkey,
+        program_id: Pubkey,
+    ) -> Result<Vec<RpcKeyedAccount>, ClientError> {
+        let pub_key = *pub_key;
         self.client
-            .get_token_accounts_by_owner(
-                pub_key,
-                solana_client::rpc_request::TokenAccountsFilter::ProgramId(*pub_key),
-            )
+            .call(|client| async move {
+                client
+                    .get_token_accounts_by_owner(
+                        &pub_key,
+                        TokenAccountsFilter::ProgramId(program_id),
+                    )
+                    .await
+            })
             .await
     }
 
@@ -57,7 +343,11 @@ impl SolanaClient {
         let signature =
             Signature::from_str(&signature).expect("Should be able to create signature");
         self.client
-            .get_transaction(&signature, UiTransactionEncoding::Json)
+            .call(|client| async move {
+                client
+                    .get_transaction(&signature, UiTransactionEncoding::Json)
+                    .await
+            })
             .await
     }
 
@@ -65,19 +355,37 @@ impl SolanaClient {
         &self,
         pub_key: &Pubkey,
     ) -> Result<Vec<RpcPrioritizationFee>, ClientError> {
+        let pub_key = *pub_key;
         self.client
-            .get_recent_prioritization_fees(&[*pub_key])
+            .call(|client| async move { client.get_recent_prioritization_fees(&[pub_key]).await })
             .await
     }
 
+    /// Fetches the confirmed transactions of the signatures in the same order, serving
+    /// finalized transactions from the transaction cache when one is configured
     pub async fn batch_process_transactions(
         &self,
         signatures: Vec<RpcConfirmedTransactionStatusWithSignature>,
     ) -> Vec<EncodedConfirmedTransactionWithStatusMeta> {
-        let mut confirmed_transactions = Vec::new();
+        let mut cached_transactions = match &self.transaction_cache {
+            Some(cache) => cache.get_transactions(
+                &signatures
+                    .iter()
+                    .filter(|sig_info| is_cacheable(sig_info))
+                    .map(|sig_info| sig_info.signature.clone())
+                    .collect::<Vec<_>>(),
+            ),
+            None => HashMap::new(),
+        };
+        let uncached_signatures: Vec<_> = signatures
+            .iter()
+            .filter(|sig_info| !cached_transactions.contains_key(&sig_info.signature))
+            .collect();
+        let mut fetched_transactions = HashMap::new();
 
-        // Process the signatures in defined chunk sizes.
-        for chunk in signatures.chunks(CHUNK_SIZE) {
+        // Process the signatures in defined chunk sizes, the rpc pool bounds how many
+        // requests of a chunk are in flight at once.
+        for chunk in uncached_signatures.chunks(CHUNK_SIZE) {
             // Create a batch of asynchronous tasks for parallel processing.
             let futures = chunk.iter().map(|sig_info| {
                 // Clone the signature string to move it into the async block.
@@ -86,40 +394,83 @@ impl SolanaClient {
                 async move {
                     let signature =
                         Signature::from_str(&sig_clone).expect("Invalid signature format");
-                    self.client
-                        .get_transaction(&signature, UiTransactionEncoding::Json)
-                        .await
+                    let result = self
+                        .client
+                        .call(|client| async move {
+                            client
+                                .get_transaction(&signature, UiTransactionEncoding::Json)
+                                .await
+                        })
+                        .await;
+                    (sig_clone, result)
                 }
             });
 
             // Await all tasks concurrently and filter out failures.
-            let results: Vec<_> = join_all(futures)
-                .await
-                .into_iter()
-                .filter_map(|tx_result| tx_result.ok())
-                .collect();
+            fetched_transactions.extend(
+                join_all(futures)
+                    .await
+                    .into_iter()
+                    .filter_map(|(signature, tx_result)| Some((signature, tx_result.ok()?))),
+            );
+        }
 
-            confirmed_transactions.extend(results);
+        if let Some(cache) = &self.transaction_cache {
+            let cacheable_transactions: Vec<_> = uncached_signatures
+                .iter()
+                .filter(|sig_info| is_cacheable(sig_info))
+                .filter_map(|sig_info| {
+                    fetched_transactions
+                        .get(&sig_info.signature)
+                        .map(|transaction| (sig_info.signature.as_str(), transaction))
+                })
+                .collect();
+            cache.insert_transactions(&cacheable_transactions);
         }
 
-        confirmed_transactions
+        signatures
+            .iter()
+            .filter_map(|sig_info| {
+                cached_transactions
+                    .remove(&sig_info.signature)
+                    .or_else(|| fetched_transactions.remove(&sig_info.signature))
+            })
+            .collect()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::wallet::wallet::Wallet;
+    use crate::{test_utils::test_utils::create_mock_signature, wallet::wallet::Wallet};
 
     use super::*;
     use dotenv::dotenv;
 
     const TEST_WALLET_ADDR: &str = "HABhDh9zrzf8mA4SBo1yro8M6AirH2hZdLNPpuvMH6iA";
 
+    #[test]
+    fn test_truncate_to_cutoff() {
+        let mut page: Vec<_> = [500, 400, 300, 200, 100]
+            .into_iter()
+            .map(|block_time| create_mock_signature("", Some(block_time)))
+            .collect();
+
+        assert!(!truncate_to_cutoff(&mut page, None));
+        assert!(!truncate_to_cutoff(&mut page, Some(100)));
+        assert_eq!(page.len(), 5);
+
+        assert!(truncate_to_cutoff(&mut page, Some(250)));
+        assert_eq!(page.len(), 3);
+    }
+
     #[tokio::test]
+    #[ignore = "requires a live RPC endpoint, see chain_data for the offline pipeline test"]
     async fn test_batch_process_transactions() {
         dotenv().ok();
         let solana_client = SolanaClient::new();
-        let wallet = Wallet::new(TEST_WALLET_ADDR, &solana_client).await;
+        let wallet = Wallet::new(TEST_WALLET_ADDR, &solana_client)
+            .await
+            .expect("Should be able to fetch wallet");
         let _ = solana_client
             .batch_process_transactions(wallet.transaction_history)
             .await;
@@ -0,0 +1,172 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use anyhow::Result;
+use log::{error, info};
+use solana_client::rpc_response::RpcConfirmedTransactionStatusWithSignature;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, TransactionConfirmationStatus,
+};
+
+use crate::database::{models::CachedTransaction, postgres::Database};
+
+/// Only finalized transactions are cached, anything less could still be rolled back
+pub fn is_cacheable(signature: &RpcConfirmedTransactionStatusWithSignature) -> bool {
+    signature.confirmation_status == Some(TransactionConfirmationStatus::Finalized)
+}
+
+/// Cumulative lookups since the worker started
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TransactionCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl TransactionCacheStats {
+    /// Percentage of looked up transactions which were cached
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / lookups as f64 * 100.0
+    }
+}
+
+/// Postgres backed cache of finalized transactions keyed by signature. It is best effort,
+/// so database failures are logged and treated as misses rather than failing the fetch
+pub struct TransactionCache {
+    database: Mutex<Database>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl TransactionCache {
+    pub fn connect() -> Result<Self> {
+        Ok(Self {
+            database: Mutex::new(Database::connect()?),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        })
+    }
+
+    pub fn stats(&self) -> TransactionCacheStats {
+        TransactionCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Looks up the cached transactions among the signatures, keyed by signature
+    pub fn get_transactions(
+        &self,
+        signatures: &[String],
+    ) -> HashMap<String, EncodedConfirmedTransactionWithStatusMeta> {
+        if signatures.is_empty() {
+            return HashMap::new();
+        }
+        let cached_transactions = self
+            .database
+            .lock()
+            .expect("Transaction cache lock poisoned")
+            .get_cached_transactions(signatures);
+        let transactions: HashMap<_, _> = match cached_transactions {
+            Ok(cached_transactions) => cached_transactions
+                .into_iter()
+                .filter_map(|cached| match serde_json::from_value(cached.transaction) {
+                    Ok(transaction) => Some((cached.signature, transaction)),
+                    Err(e) => {
+                        error!(
+                            "Failed to deserialize cached transaction {}: {:?}",
+                            cached.signature, e
+                        );
+                        None
+                    }
+                })
+                .collect(),
+            Err(e) => {
+                error!("Failed to read transaction cache: {:?}", e);
+                HashMap::new()
+            }
+        };
+
+        let hits = transactions.len() as u64;
+        let misses = signatures.len() as u64 - hits;
+        self.hits.fetch_add(hits, Ordering::Relaxed);
+        self.misses.fetch_add(misses, Ordering::Relaxed);
+        info!(
+            "Transaction cache: {} hits, {} misses, {:.2}% hit rate since start",
+            hits,
+            misses,
+            self.stats().hit_rate()
+        );
+        transactions
+    }
+
+    pub fn insert_transactions(
+        &self,
+        transactions: &[(&str, &EncodedConfirmedTransactionWithStatusMeta)],
+    ) {
+        let cached_transactions: Vec<_> = transactions
+            .iter()
+            .filter_map(
+                |(signature, transaction)| match serde_json::to_value(transaction) {
+                    Ok(value) => Some(CachedTransaction::new(
+                        signature.to_string(),
+                        transaction.slot as i64,
+                        value,
+                    )),
+                    Err(e) => {
+                        error!("Failed to serialize transaction {}: {:?}", signature, e);
+                        None
+                    }
+                },
+            )
+            .collect();
+        if cached_transactions.is_empty() {
+            return;
+        }
+        if let Err(e) = self
+            .database
+            .lock()
+            .expect("Transaction cache lock poisoned")
+            .insert_cached_transactions(&cached_transactions)
+        {
+            error!("Failed to write transaction cache: {:?}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::test_utils::create_mock_signature;
+
+    use super::*;
+
+    #[test]
+    fn test_is_cacheable() {
+        let signature = |confirmation_status| RpcConfirmedTransactionStatusWithSignature {
+            confirmation_status,
+            ..create_mock_signature("", None)
+        };
+        assert!(is_cacheable(&signature(Some(
+            TransactionConfirmationStatus::Finalized
+        ))));
+        assert!(!is_cacheable(&signature(Some(
+            TransactionConfirmationStatus::Confirmed
+        ))));
+        assert!(!is_cacheable(&signature(None)));
+    }
+
+    #[test]
+    fn test_hit_rate() {
+        assert_eq!(TransactionCacheStats::default().hit_rate(), 0.0);
+        let stats = TransactionCacheStats { hits: 3, misses: 1 };
+        assert_eq!(stats.hit_rate(), 75.0);
+    }
+}
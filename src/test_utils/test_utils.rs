@@ -0,0 +1,57 @@
+use solana_client::rpc_response::RpcConfirmedTransactionStatusWithSignature;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+
+pub fn create_mock_signature(
+    signature: &str,
+    block_time: Option<i64>,
+) -> RpcConfirmedTransactionStatusWithSignature {
+    RpcConfirmedTransactionStatusWithSignature {
+        signature: signature.to_string(),
+        slot: 0,
+        err: None,
+        memo: None,
+        block_time,
+        confirmation_status: None,
+    }
+}
+
+/// A successful transaction in the json encoding, moving SOL between the accounts as given by
+/// their balances
+pub fn create_mock_transaction(
+    signature: &str,
+    account_keys: &[&str],
+    pre_balances: &[u64],
+    post_balances: &[u64],
+    block_time: i64,
+) -> EncodedConfirmedTransactionWithStatusMeta {
+    serde_json::from_value(serde_json::json!({
+        "slot": 1,
+        "blockTime": block_time,
+        "transaction": {
+            "signatures": [signature],
+            "message": {
+                "accountKeys": account_keys,
+                "header": {
+                    "numRequiredSignatures": 1,
+                    "numReadonlySignedAccounts": 0,
+                    "numReadonlyUnsignedAccounts": 1
+                },
+                "recentBlockhash": "11111111111111111111111111111111",
+                "instructions": []
+            }
+        },
+        "meta": {
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5000,
+            "preBalances": pre_balances,
+            "postBalances": post_balances,
+            "innerInstructions": null,
+            "logMessages": null,
+            "preTokenBalances": null,
+            "postTokenBalances": null,
+            "rewards": null
+        }
+    }))
+    .unwrap()
+}
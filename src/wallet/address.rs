@@ -0,0 +1,121 @@
+use std::{fmt, str::FromStr};
+
+use solana_sdk::pubkey::{ParsePubkeyError, Pubkey};
+
+/// Native programs, sysvars and widely used SPL programs, none of which can hold a reputation
+const RESERVED_ADDRESSES: [&str; 28] = [
+    "11111111111111111111111111111111",
+    "Vote111111111111111111111111111111111111111",
+    "Stake11111111111111111111111111111111111111",
+    "Config1111111111111111111111111111111111111",
+    "BPFLoader1111111111111111111111111111111111",
+    "BPFLoader2111111111111111111111111111111111",
+    "BPFLoaderUpgradeab1e11111111111111111111111",
+    "ComputeBudget111111111111111111111111111111",
+    "AddressLookupTab1e1111111111111111111111111",
+    "Ed25519SigVerify111111111111111111111111111",
+    "KeccakSecp256k11111111111111111111111111111",
+    "1nc1nerator11111111111111111111111111111111",
+    "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+    "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb",
+    "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL",
+    "Sysvar1111111111111111111111111111111111111",
+    "SysvarC1ock11111111111111111111111111111111",
+    "SysvarRent111111111111111111111111111111111",
+    "SysvarEpochSchedu1e111111111111111111111111",
+    "SysvarRecentB1ockHashes11111111111111111111",
+    "SysvarS1otHashes111111111111111111111111111",
+    "SysvarStakeHistory1111111111111111111111111",
+    "Sysvar1nstructions1111111111111111111111111",
+    "SysvarFees111111111111111111111111111111111",
+    "SysvarRewards111111111111111111111111111111",
+    "SysvarS1otHistory11111111111111111111111111",
+    "SysvarEpochRewards1111111111111111111111111",
+    "SysvarLastRestartS1ot1111111111111111111111",
+];
+
+#[derive(Debug, PartialEq)]
+pub enum InvalidWalletAddress {
+    Empty,
+    /// Not base58, or too long to be a public key
+    Malformed,
+    /// Doesn't decode to 32 bytes
+    WrongSize,
+    /// A program or sysvar address rather than a wallet
+    Reserved,
+    /// An executable account, i.e. a program missing from the reserved addresses
+    Executable,
+}
+
+impl fmt::Display for InvalidWalletAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            InvalidWalletAddress::Empty => "Wallet address is empty",
+            InvalidWalletAddress::Malformed => "Wallet address isn't a valid base58 public key",
+            InvalidWalletAddress::WrongSize => "Wallet address doesn't decode to 32 bytes",
+            InvalidWalletAddress::Reserved => {
+                "Wallet address belongs to a program or sysvar, not a wallet"
+            }
+            InvalidWalletAddress::Executable => {
+                "Wallet address belongs to an executable program account, not a wallet"
+            }
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+impl std::error::Error for InvalidWalletAddress {}
+
+/// Parses a wallet address, rejecting anything which can't be a user wallet
+pub fn parse_wallet_addr(wallet_addr: &str) -> Result<Pubkey, InvalidWalletAddress> {
+    if wallet_addr.trim().is_empty() {
+        return Err(InvalidWalletAddress::Empty);
+    }
+    let pub_key = Pubkey::from_str(wallet_addr).map_err(|err| match err {
+        ParsePubkeyError::WrongSize => InvalidWalletAddress::WrongSize,
+        _ => InvalidWalletAddress::Malformed,
+    })?;
+    if RESERVED_ADDRESSES.contains(&wallet_addr) {
+        return Err(InvalidWalletAddress::Reserved);
+    }
+    Ok(pub_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wallet_addr() {
+        let test_cases = vec![
+            ("", Err(InvalidWalletAddress::Empty)),
+            ("not a wallet", Err(InvalidWalletAddress::Malformed)),
+            ("0OIl", Err(InvalidWalletAddress::Malformed)),
+            ("abc", Err(InvalidWalletAddress::WrongSize)),
+            (
+                "11111111111111111111111111111111",
+                Err(InvalidWalletAddress::Reserved),
+            ),
+            (
+                "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+                Err(InvalidWalletAddress::Reserved),
+            ),
+        ];
+        for (wallet_addr, expected) in test_cases {
+            assert_eq!(parse_wallet_addr(wallet_addr).map(|_| ()), expected);
+        }
+
+        let wallet_addr = "CKs1E69a2e9TmH4mKKLrXFF8kD3ZnwKjoEuXa6sz9WqX";
+        assert_eq!(
+            parse_wallet_addr(wallet_addr).unwrap().to_string(),
+            wallet_addr
+        );
+    }
+
+    #[test]
+    fn test_reserved_addresses_are_valid_pubkeys() {
+        for wallet_addr in RESERVED_ADDRESSES {
+            assert!(Pubkey::from_str(wallet_addr).is_ok(), "{}", wallet_addr);
+        }
+    }
+}
@@ -1 +1,3 @@
+pub mod address;
+pub mod token_holding;
 pub mod wallet;
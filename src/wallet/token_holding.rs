@@ -0,0 +1,115 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_response::RpcKeyedAccount;
+
+/// `info` of a `jsonParsed` SPL Token or Token-2022 account
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParsedTokenAccount {
+    mint: String,
+    token_amount: ParsedTokenAmount,
+}
+
+#[derive(Deserialize)]
+struct ParsedTokenAmount {
+    amount: String,
+    decimals: u8,
+}
+
+/// Balance of a single token account owned by the wallet
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TokenHolding {
+    pub token_account: String,
+    pub mint: String,
+    /// Raw amount, in the smallest unit of the mint
+    pub amount: u64,
+    pub decimals: u8,
+}
+
+impl TokenHolding {
+    pub fn ui_amount(&self) -> f64 {
+        self.amount as f64 / 10f64.powi(self.decimals as i32)
+    }
+
+    fn from_keyed_account(keyed_account: &RpcKeyedAccount) -> Option<Self> {
+        let data = serde_json::to_value(&keyed_account.account.data).ok()?;
+        let parsed: ParsedTokenAccount =
+            serde_json::from_value(data.pointer("/parsed/info")?.clone()).ok()?;
+        Some(Self {
+            token_account: keyed_account.pubkey.clone(),
+            mint: parsed.mint,
+            amount: parsed.token_amount.amount.parse().ok()?,
+            decimals: parsed.token_amount.decimals,
+        })
+    }
+}
+
+/// Parses the `jsonParsed` token accounts of a wallet, skipping accounts in any other encoding
+pub fn parse_token_holdings(token_accounts: &[RpcKeyedAccount]) -> Vec<TokenHolding> {
+    token_accounts
+        .iter()
+        .filter_map(|keyed_account| {
+            let holding = TokenHolding::from_keyed_account(keyed_account);
+            if holding.is_none() {
+                warn!(
+                    "Skipping unparseable token account: {}",
+                    keyed_account.pubkey
+                );
+            }
+            holding
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_mock_keyed_account(data: serde_json::Value) -> RpcKeyedAccount {
+        serde_json::from_value(serde_json::json!({
+            "pubkey": "4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T",
+            "account": {
+                "lamports": 2039280,
+                "data": data,
+                "owner": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+                "executable": false,
+                "rentEpoch": 0,
+                "space": 165
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_token_holdings() {
+        let parsed = create_mock_keyed_account(serde_json::json!({
+            "program": "spl-token",
+            "parsed": {
+                "type": "account",
+                "info": {
+                    "isNative": false,
+                    "mint": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+                    "owner": "CKs1E69a2e9TmH4mKKLrXFF8kD3ZnwKjoEuXa6sz9WqX",
+                    "state": "initialized",
+                    "tokenAmount": {
+                        "amount": "1500000",
+                        "decimals": 6,
+                        "uiAmount": 1.5,
+                        "uiAmountString": "1.5"
+                    }
+                }
+            },
+            "space": 165
+        }));
+        let binary = create_mock_keyed_account(serde_json::json!(["", "base64"]));
+
+        let holdings = parse_token_holdings(&[parsed, binary]);
+        assert_eq!(holdings.len(), 1);
+        assert_eq!(
+            holdings[0].mint,
+            "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+        );
+        assert_eq!(holdings[0].amount, 1_500_000);
+        assert_eq!(holdings[0].ui_amount(), 1.5);
+    }
+}
@@ -1,23 +1,87 @@
-use solana_client::rpc_response::{
-    RpcConfirmedTransactionStatusWithSignature, RpcKeyedAccount, RpcPrioritizationFee,
+use std::fmt;
+
+use log::warn;
+use solana_client::{
+    client_error::ClientError,
+    rpc_response::{RpcConfirmedTransactionStatusWithSignature, RpcPrioritizationFee},
 };
-use solana_sdk::{account::Account, pubkey::Pubkey};
+use solana_sdk::account::Account;
+
+use crate::solana_client::chain_data::ChainDataSource;
+
+use super::{
+    address::{parse_wallet_addr, InvalidWalletAddress},
+    token_holding::{parse_token_holdings, TokenHolding},
+};
+
+#[derive(Debug)]
+pub enum WalletError {
+    InvalidAddress(InvalidWalletAddress),
+    /// The account doesn't exist and never sent or received a transaction
+    AccountNotFound,
+    /// The RPC node couldn't be reached or failed to answer, retrying may succeed
+    Rpc(Box<ClientError>),
+}
+
+impl WalletError {
+    /// Whether fetching the wallet again would fail the same way
+    pub fn is_permanent(&self) -> bool {
+        !matches!(self, WalletError::Rpc(_))
+    }
+}
+
+impl fmt::Display for WalletError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalletError::InvalidAddress(_) => write!(f, "Invalid wallet address"),
+            WalletError::AccountNotFound => write!(f, "Wallet account doesn't exist"),
+            WalletError::Rpc(_) => write!(f, "Failed to fetch wallet from RPC"),
+        }
+    }
+}
+
+impl std::error::Error for WalletError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WalletError::InvalidAddress(err) => Some(err),
+            WalletError::AccountNotFound => None,
+            WalletError::Rpc(err) => Some(err.as_ref()),
+        }
+    }
+}
 
-use crate::solana_client::solana_client::SolanaClient;
+impl From<InvalidWalletAddress> for WalletError {
+    fn from(err: InvalidWalletAddress) -> Self {
+        WalletError::InvalidAddress(err)
+    }
+}
+
+impl From<ClientError> for WalletError {
+    fn from(err: ClientError) -> Self {
+        WalletError::Rpc(Box::new(err))
+    }
+}
 
 #[derive(Debug)]
 pub struct Wallet {
     pub account_balance: u64,
     pub account_info: Account,
     pub transaction_history: Vec<RpcConfirmedTransactionStatusWithSignature>,
-    pub token_accounts: Vec<RpcKeyedAccount>,
+    pub token_holdings: Vec<TokenHolding>,
+    /// Unix timestamp of the wallet's first-ever transaction
+    pub first_seen: Option<i64>,
+    /// The wallet's first-ever transaction
+    pub first_transaction: Option<RpcConfirmedTransactionStatusWithSignature>,
     pub wallet_addr: String,
     pub prioritization_fees: Vec<RpcPrioritizationFee>,
 }
 
 impl Wallet {
-    pub async fn new(wallet_addr: &str, solana_client: &SolanaClient) -> Self {
-        let pub_key = Pubkey::from_str_const(wallet_addr);
+    pub async fn new(
+        wallet_addr: &str,
+        chain_data: &dyn ChainDataSource,
+    ) -> Result<Self, WalletError> {
+        let pub_key = parse_wallet_addr(wallet_addr)?;
 
         let (
             account_balance,
@@ -26,20 +90,72 @@ impl Wallet {
             token_accounts,
             prioritization_fees,
         ) = tokio::join!(
-            solana_client.get_account_balance(&pub_key),
-            solana_client.get_account_info(&pub_key),
-            solana_client.get_transaction_history(&pub_key),
-            solana_client.get_token_accounts(&pub_key),
-            solana_client.get_recent_prioritization_fees(&pub_key),
+            chain_data.get_account_balance(&pub_key),
+            chain_data.get_account_info(&pub_key),
+            chain_data.get_transaction_history(&pub_key),
+            chain_data.get_token_accounts(&pub_key),
+            chain_data.get_recent_prioritization_fees(&pub_key),
         );
+        let transaction_history = transaction_history?;
+        let account_info = account_info?;
+        // programs missing from the reserved addresses are only recognisable by their account
+        if account_info
+            .as_ref()
+            .is_some_and(|account_info| account_info.executable)
+        {
+            return Err(InvalidWalletAddress::Executable.into());
+        }
+
+        // the history is bounded, so the walk to the first transaction continues where it stopped
+        let oldest_signature = chain_data
+            .get_oldest_signature(&pub_key, transaction_history.last())
+            .await;
+        let first_transaction = match oldest_signature {
+            Ok(oldest_signature) => oldest_signature,
+            // the walk only dates the wallet, so an incomplete one leaves its age unknown
+            Err(e) => {
+                warn!(
+                    "Failed to find the first transaction of {}, its age is unknown: {}",
+                    wallet_addr, e
+                );
+                None
+            }
+        };
+        let first_seen = first_transaction.as_ref().and_then(|sig| sig.block_time);
+
+        // a wallet which sent away all of its lamports is closed, but still has a history to rate
+        let account_info = match account_info {
+            Some(account_info) => account_info,
+            None if transaction_history.is_empty() => return Err(WalletError::AccountNotFound),
+            None => Account::default(),
+        };
 
-        Self {
-            account_balance: account_balance.unwrap_or_default(),
-            account_info: account_info.unwrap_or_default(),
-            transaction_history: transaction_history.unwrap_or_default(),
-            token_accounts: token_accounts.unwrap_or_default(),
+        Ok(Self {
+            account_balance: account_balance?,
+            account_info,
+            transaction_history,
+            token_holdings: parse_token_holdings(&token_accounts?),
+            first_seen,
+            first_transaction,
             wallet_addr: wallet_addr.to_string(),
-            prioritization_fees: prioritization_fees.unwrap_or_default(),
-        }
+            prioritization_fees: prioritization_fees?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_client::client_error::ClientErrorKind;
+
+    use super::*;
+
+    #[test]
+    fn test_wallet_error_is_permanent() {
+        assert!(WalletError::InvalidAddress(InvalidWalletAddress::Malformed).is_permanent());
+        assert!(WalletError::AccountNotFound.is_permanent());
+        assert!(!WalletError::Rpc(Box::new(
+            ClientErrorKind::Custom("timeout".to_string()).into()
+        ))
+        .is_permanent());
     }
 }
@@ -0,0 +1,417 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use anyhow::{bail, Error, Result};
+use chrono::{NaiveDateTime, Utc};
+use hmac::{Hmac, Mac};
+use log::{error, info, warn};
+use reqwest::{redirect::Policy, Client, Url};
+use serde::Serialize;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::database::{
+    models::{RatingClassification, WalletReport, Webhook, WebhookDelivery, WebhookDeliveryStatus},
+    postgres::Database,
+};
+
+const MAX_DELIVERY_ATTEMPTS: i32 = 5;
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// Untouched for this long, an unfinished delivery is no longer being attempted by the task
+/// that created it, and doubles with every attempt made so far
+const REDRIVE_INITIAL_DELAY: Duration = Duration::from_secs(5 * 60);
+const REDRIVE_BATCH_SIZE: i64 = 100;
+const SIGNATURE_HEADER: &str = "X-SolAnalystAI-Signature";
+const EVENT_HEADER: &str = "X-SolAnalystAI-Event";
+const DELIVERY_HEADER: &str = "X-SolAnalystAI-Delivery";
+const CLASSIFICATION_CHANGED_EVENT: &str = "classification_changed";
+
+#[derive(Serialize, Debug)]
+pub struct ClassificationChangedEvent {
+    pub event: String,
+    pub wallet_addr: String,
+    pub report_id: Uuid,
+    pub previous_report_id: Uuid,
+    pub previous_classification: RatingClassification,
+    pub new_classification: RatingClassification,
+    pub previous_score: i32,
+    pub new_score: i32,
+}
+
+impl ClassificationChangedEvent {
+    /// Returns an event if the classification differs between the two reports
+    pub fn new(previous_report: &WalletReport, report: &WalletReport) -> Option<Self> {
+        (previous_report.rating_classification != report.rating_classification).then(|| Self {
+            event: CLASSIFICATION_CHANGED_EVENT.to_string(),
+            wallet_addr: report.wallet_addr.clone(),
+            report_id: report.id,
+            previous_report_id: previous_report.id,
+            previous_classification: previous_report.rating_classification.clone(),
+            new_classification: report.rating_classification.clone(),
+            previous_score: previous_report.rating_score,
+            new_score: report.rating_score,
+        })
+    }
+}
+
+/// Whether the address is reachable on the public internet. Webhooks must not reach the
+/// service's own network, e.g. loopback, private ranges or the cloud metadata endpoint
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, third, _] = ip.octets();
+            let this_network = first == 0;
+            let shared = first == 100 && (second & 0xc0) == 64;
+            let protocol_assignments = first == 192 && second == 0 && third == 0;
+            let benchmarking = first == 198 && (second & 0xfe) == 18;
+            // also covers the broadcast address
+            let reserved = first >= 240;
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || this_network
+                || shared
+                || protocol_assignments
+                || benchmarking
+                || reserved)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_public_ip(IpAddr::V4(mapped)),
+            None => {
+                let segments = ip.segments();
+                let unique_local = (segments[0] & 0xfe00) == 0xfc00;
+                let link_local = (segments[0] & 0xffc0) == 0xfe80;
+                // NAT64 and 6to4 addresses are translated to an embedded ipv4 address, which
+                // may well be an internal one
+                let nat64 = segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0];
+                let six_to_four = segments[0] == 0x2002;
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || unique_local
+                    || link_local
+                    || nat64
+                    || six_to_four)
+            }
+        },
+    }
+}
+
+/// Host and port of an http(s) url
+fn webhook_host(url: &str) -> Result<(String, u16)> {
+    let url = Url::parse(url)?;
+    match url.scheme() {
+        "http" | "https" => {}
+        scheme => bail!("Unsupported url scheme: {}", scheme),
+    }
+    let Some(host) = url.host_str() else {
+        bail!("Webhook url has no host");
+    };
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| Error::msg("Webhook url has no port"))?;
+    // ipv6 hosts are bracketed in urls but not when resolved
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    Ok((host.to_string(), port))
+}
+
+/// The address to deliver to, as long as every address the host resolves to is public
+fn public_addr(host: &str, addrs: Vec<SocketAddr>) -> Result<SocketAddr> {
+    if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        bail!(
+            "Webhook host {} resolves to non public address {}",
+            host,
+            addr.ip()
+        );
+    }
+    addrs
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::msg(format!("Webhook host {} doesn't resolve", host)))
+}
+
+/// Only http(s) urls of hosts resolving to public addresses can be registered as webhooks
+pub async fn validate_webhook_url(url: &str) -> Result<()> {
+    resolve_webhook_url(url).await?;
+    Ok(())
+}
+
+/// Resolves the webhook url again at delivery, as the host's addresses may have changed since
+/// registration, returning the host and the checked address to connect to
+async fn resolve_webhook_url(url: &str) -> Result<(String, SocketAddr)> {
+    let (host, port) = webhook_host(url)?;
+    let addrs = tokio::net::lookup_host((host.as_str(), port))
+        .await?
+        .collect();
+    let addr = public_addr(&host, addrs)?;
+    Ok((host, addr))
+}
+
+/// Hex encoded HMAC-SHA256 of the payload, keyed with the webhook secret
+pub fn sign_payload(secret: &str, payload: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(payload);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[derive(Default)]
+pub struct WebhookClient;
+
+impl WebhookClient {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// A client connecting only to the checked address, so a host can't be re-resolved to a
+    /// private address between the check and the request, and which doesn't follow redirects
+    fn pinned_client(host: &str, addr: SocketAddr) -> Result<Client> {
+        Ok(Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .redirect(Policy::none())
+            .resolve(host, addr)
+            .build()?)
+    }
+
+    /// Records a pending delivery for every webhook watching the wallet and delivers them from
+    /// a background task, so slow or dead endpoints don't hold up the report queue
+    pub fn notify_classification_change(
+        &self,
+        database: &mut Database,
+        event: &ClassificationChangedEvent,
+    ) -> Result<()> {
+        let webhooks = database.get_wallet_webhooks(&event.wallet_addr)?;
+        info!(
+            "Notifying {} webhooks of classification change for wallet: {} ({:?} -> {:?})",
+            webhooks.len(),
+            event.wallet_addr,
+            event.previous_classification,
+            event.new_classification
+        );
+        if webhooks.is_empty() {
+            return Ok(());
+        }
+
+        let payload = serde_json::to_value(event)?;
+        let mut deliveries = Vec::with_capacity(webhooks.len());
+        for webhook in webhooks {
+            let delivery = WebhookDelivery::new(webhook.id, event.report_id, payload.clone());
+            database.insert_webhook_delivery(&delivery)?;
+            deliveries.push((webhook, delivery));
+        }
+        tokio::spawn(Self::deliver_all(deliveries));
+        Ok(())
+    }
+
+    /// Retries the pending and failed deliveries left behind by a restart, a crash or an
+    /// earlier refusal, once their backoff has passed and while they have attempts left
+    pub fn redrive_unfinished_deliveries(&self, database: &mut Database) -> Result<()> {
+        let now = Utc::now().naive_local();
+        let deliveries: Vec<_> = database
+            .get_unfinished_webhook_deliveries(
+                MAX_DELIVERY_ATTEMPTS,
+                now - REDRIVE_INITIAL_DELAY,
+                REDRIVE_BATCH_SIZE,
+            )?
+            .into_iter()
+            .filter(|(_, delivery)| redrive_due(delivery, now))
+            .collect();
+        if deliveries.is_empty() {
+            return Ok(());
+        }
+        info!(
+            "Redriving {} unfinished webhook deliveries",
+            deliveries.len()
+        );
+        tokio::spawn(Self::deliver_all(deliveries));
+        Ok(())
+    }
+
+    /// Delivers with a database connection of its own, as it outlives the report job
+    async fn deliver_all(deliveries: Vec<(Webhook, WebhookDelivery)>) {
+        let mut database = match Database::connect() {
+            Ok(database) => database,
+            Err(e) => {
+                error!(
+                    "Failed to connect to the database to deliver webhooks: {:?}",
+                    e
+                );
+                return;
+            }
+        };
+        for (webhook, mut delivery) in deliveries {
+            Self::deliver(&mut database, &webhook, &mut delivery).await;
+        }
+    }
+
+    /// Keeps the delivery row up to date as the record of its attempts
+    fn record(database: &mut Database, delivery: &WebhookDelivery) {
+        if let Err(e) = database.update_webhook_delivery(delivery) {
+            error!("Failed to record webhook delivery {}: {:?}", delivery.id, e);
+        }
+    }
+
+    /// Attempts delivery with exponential backoff, recording the outcome of every attempt
+    async fn deliver(database: &mut Database, webhook: &Webhook, delivery: &mut WebhookDelivery) {
+        let body = delivery.payload.to_string();
+        let signature = sign_payload(&webhook.secret, body.as_bytes());
+        let mut retry_delay = INITIAL_RETRY_DELAY;
+
+        while delivery.attempts < MAX_DELIVERY_ATTEMPTS {
+            delivery.attempts += 1;
+            let client = match resolve_webhook_url(&webhook.url)
+                .await
+                .and_then(|(host, addr)| Self::pinned_client(&host, addr))
+            {
+                Ok(client) => client,
+                Err(e) => {
+                    error!("Refusing webhook delivery {}: {}", delivery.id, e);
+                    delivery.last_error = Some(e.to_string());
+                    delivery.updated_at = Utc::now().naive_local();
+                    delivery.status = WebhookDeliveryStatus::Failed;
+                    Self::record(database, delivery);
+                    return;
+                }
+            };
+            let response = client
+                .post(&webhook.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(SIGNATURE_HEADER, &signature)
+                .header(EVENT_HEADER, CLASSIFICATION_CHANGED_EVENT)
+                .header(DELIVERY_HEADER, delivery.id.to_string())
+                .body(body.clone())
+                .send()
+                .await;
+            delivery.updated_at = Utc::now().naive_local();
+
+            match response {
+                Ok(response) => {
+                    delivery.response_status = Some(response.status().as_u16() as i32);
+                    if response.status().is_success() {
+                        delivery.status = WebhookDeliveryStatus::Delivered;
+                        delivery.last_error = None;
+                        info!(
+                            "Delivered webhook {} on attempt {}",
+                            delivery.id, delivery.attempts
+                        );
+                        Self::record(database, delivery);
+                        return;
+                    }
+                    delivery.last_error = Some(format!(
+                        "Unsuccessful response status: {}",
+                        response.status()
+                    ));
+                }
+                Err(e) => delivery.last_error = Some(e.to_string()),
+            }
+
+            warn!(
+                "Webhook delivery {} attempt {} failed: {:?}",
+                delivery.id, delivery.attempts, delivery.last_error
+            );
+            if delivery.attempts < MAX_DELIVERY_ATTEMPTS {
+                Self::record(database, delivery);
+                tokio::time::sleep(retry_delay).await;
+                retry_delay *= 2;
+            }
+        }
+
+        error!(
+            "Giving up on webhook delivery {} after {} attempts",
+            delivery.id, delivery.attempts
+        );
+        delivery.status = WebhookDeliveryStatus::Failed;
+        Self::record(database, delivery);
+    }
+}
+
+/// Whether the backoff of an unfinished delivery has passed since its last attempt
+fn redrive_due(delivery: &WebhookDelivery, now: NaiveDateTime) -> bool {
+    let backoff = REDRIVE_INITIAL_DELAY * 2u32.pow(delivery.attempts.clamp(0, 16) as u32);
+    delivery.updated_at + backoff <= now
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_payload() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign_payload("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_webhook_url() {
+        assert!(validate_webhook_url("https://93.184.215.14/hook")
+            .await
+            .is_ok());
+        assert!(validate_webhook_url("https://[2606:4700::1111]/hook")
+            .await
+            .is_ok());
+        assert!(validate_webhook_url("ftp://example.com").await.is_err());
+        assert!(validate_webhook_url("not a url").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_webhook_url_rejects_internal_hosts() {
+        for url in [
+            "http://localhost:3000",
+            "http://127.0.0.1/hook",
+            "http://10.0.0.1/hook",
+            "http://192.168.1.1/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/hook",
+            "http://[fd00::1]/hook",
+            "http://[::ffff:127.0.0.1]/hook",
+            "http://0.0.0.0/hook",
+            "http://0.1.2.3/hook",
+            "http://192.0.0.8/hook",
+            "http://198.18.0.1/hook",
+            "http://198.19.255.254/hook",
+            "http://240.0.0.1/hook",
+            "http://255.255.255.255/hook",
+            "http://[64:ff9b::a9fe:a9fe]/hook",
+            "http://[2002:7f00:1::]/hook",
+        ] {
+            assert!(
+                validate_webhook_url(url).await.is_err(),
+                "{} is internal",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_redrive_due() {
+        let mut delivery = WebhookDelivery::new(Uuid::new_v4(), Uuid::new_v4(), "{}".into());
+        let last_attempt = delivery.updated_at;
+        assert!(!redrive_due(
+            &delivery,
+            last_attempt + Duration::from_secs(4 * 60)
+        ));
+        assert!(redrive_due(
+            &delivery,
+            last_attempt + Duration::from_secs(5 * 60)
+        ));
+
+        delivery.attempts = 2;
+        assert!(!redrive_due(
+            &delivery,
+            last_attempt + Duration::from_secs(19 * 60)
+        ));
+        assert!(redrive_due(
+            &delivery,
+            last_attempt + Duration::from_secs(20 * 60)
+        ));
+    }
+}
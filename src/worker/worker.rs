@@ -4,10 +4,17 @@ use log::{error, info, warn};
 use pulsar::SubType;
 
 use crate::{
+    case_report::generator::{case_report_generator_from_env, CaseReportGenerator},
     database::postgres::Database,
-    openai_client::openai_client::OpenAIClient,
-    pulsar::pulsar::{PulsarClient, PulsarConsumer},
-    solana_client::solana_client::SolanaClient,
+    jobs::async_jobs::is_permanent_failure,
+    progress::progress::WALLET_REPORT_PROGRESS_TOPIC,
+    pulsar::pulsar::{PulsarClient, PulsarConsumer, PulsarProducer},
+    reputation::{indicator_registry::IndicatorRegistry, scoring_policy::ScoringPolicy},
+    solana_client::{
+        chain_data::{chain_data_source_from_env, ChainDataSource},
+        solana_client::TransactionHistoryConfig,
+    },
+    webhooks::webhooks::WebhookClient,
 };
 
 pub const WALLET_REPUTATION_TOPIC: &str = "non-persistent://public/default/wallet-reputation";
@@ -16,8 +23,13 @@ const SUB_TYPE: SubType = SubType::Shared;
 
 pub struct WalletReportWorker {
     pub database: Database,
-    pub solana_client: SolanaClient,
-    pub openai_client: OpenAIClient,
+    pub chain_data: Box<dyn ChainDataSource>,
+    pub history_config: TransactionHistoryConfig,
+    pub case_report_generator: Box<dyn CaseReportGenerator>,
+    pub scoring_policy: ScoringPolicy,
+    pub indicator_registry: IndicatorRegistry,
+    pub webhook_client: WebhookClient,
+    pub progress_producer: PulsarProducer,
     job_consumer: PulsarConsumer,
 }
 
@@ -25,10 +37,22 @@ impl WalletReportWorker {
     pub async fn new() -> Self {
         info!("Initializing WalletReportWorker...");
         let pulsar_client = PulsarClient::new().await;
+        let scoring_policy = ScoringPolicy::load().expect("Should be able to load scoring policy");
+        let indicator_registry = IndicatorRegistry::from_policy(&scoring_policy);
+        info!("Enabled indicators: {:?}", indicator_registry.names());
         Self {
             database: Database::connect().expect("Should be able to connect to db"),
-            solana_client: SolanaClient::new(),
-            openai_client: OpenAIClient::new(),
+            chain_data: chain_data_source_from_env()
+                .expect("Should be able to create chain data source"),
+            history_config: TransactionHistoryConfig::from_env(),
+            case_report_generator: case_report_generator_from_env()
+                .expect("Should be able to create case report generator"),
+            scoring_policy,
+            indicator_registry,
+            webhook_client: WebhookClient::new(),
+            progress_producer: pulsar_client
+                .create_producer(WALLET_REPORT_PROGRESS_TOPIC)
+                .await,
             job_consumer: pulsar_client
                 .create_consumer(vec![WALLET_REPUTATION_TOPIC], SUB_TYPE, SUB)
                 .await,
@@ -59,6 +83,13 @@ impl WalletReportWorker {
                     self.job_consumer.ack(&msg).await;
                     info!("Job processed successfully");
                 }
+                Err(e) if is_permanent_failure(&e) => {
+                    self.job_consumer.ack(&msg).await;
+                    warn!(
+                        "Job failed permanently with error: {:?}. Message acknowledged without retry.",
+                        e
+                    );
+                }
                 Err(e) => {
                     self.job_consumer.nack(&msg).await;
                     warn!(